@@ -0,0 +1,211 @@
+//! Problems found in Nix source, reported by the parser, lints and refactors
+//! alike. See `docs/diagnostics.md` for the list of codes.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::parser::ast::core::Span;
+
+/// Stable diagnostic codes. Codes are never reused once published.
+pub mod codes {
+    pub const EXPECTED_EXPRESSION: &str = "NL0001";
+    pub const UNCLOSED_DELIMITER: &str = "NL0002";
+    pub const UNEXPECTED_INPUT: &str = "NL0003";
+    pub const INVALID_BINDING: &str = "NL0004";
+    pub const INVALID_LIST_ELEMENT: &str = "NL0005";
+    pub const INTEGER_OUT_OF_RANGE: &str = "NL0006";
+    pub const FLOAT_OUT_OF_RANGE: &str = "NL0007";
+    pub const NON_ASSOCIATIVE_CHAIN: &str = "NL0008";
+    pub const LEGACY_OPERATOR: &str = "NL0009";
+    pub const INVALID_OPERATOR: &str = "NL0010";
+    pub const TOO_DEEPLY_NESTED: &str = "NL0011";
+    pub const RENDER_FAILED: &str = "NL0100";
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// A span with a short explanation, shown underneath the source line.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Span, message: String) -> Self {
+        Self { span, message }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: String,
+    pub message: String,
+    /// Where the problem is; underlined with `^`.
+    pub span: Span,
+    /// Text printed next to the primary underline.
+    pub label: Option<String>,
+    /// Related places, underlined with `-`.
+    pub secondary: Vec<Label>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &str, message: String, span: Span) -> Self {
+        Self {
+            severity,
+            code: code.to_string(),
+            message,
+            span,
+            label: None,
+            secondary: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn error(code: &str, message: String, span: Span) -> Self {
+        Self::new(Severity::Error, code, message, span)
+    }
+
+    pub fn warning(code: &str, message: String, span: Span) -> Self {
+        Self::new(Severity::Warning, code, message, span)
+    }
+
+    pub fn with_label(mut self, label: String) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: String) -> Self {
+        self.secondary.push(Label::new(span, message));
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    /// Renders the diagnostic with an excerpt of `source`, rustc style:
+    ///
+    /// ```text
+    /// error[NL0001]: expected an expression
+    ///  --> default.nix:1:7
+    ///   |
+    /// 1 | { a = ; b = 2; }
+    ///   |       ^ expected an expression here
+    /// ```
+    pub fn render(&self, source: &str, file_name: &str) -> String {
+        let lines: Vec<&str> = source
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line))
+            .collect();
+
+        let mut labels = vec![(&self.span, self.label.as_deref().unwrap_or(""), '^')];
+        labels.extend(self.secondary.iter().map(|l| (&l.span, l.message.as_str(), '-')));
+
+        let mut shown: Vec<usize> = labels
+            .iter()
+            .flat_map(|(span, _, _)| line_number(span.start.line)..=line_number(span.end.line))
+            .filter(|line| *line <= lines.len())
+            .collect();
+        shown.sort_unstable();
+        shown.dedup();
+
+        let width = shown.last().map_or(1, |line| line.to_string().len());
+        let pad = " ".repeat(width);
+
+        let mut out = format!("{}[{}]: {}\n", self.severity, self.code, self.message);
+        out.push_str(&format!(
+            "{}--> {}:{}:{}\n",
+            pad, file_name, self.span.start.line, self.span.start.column
+        ));
+        out.push_str(&format!("{} |\n", pad));
+
+        let mut previous: Option<usize> = None;
+        for &line in &shown {
+            if previous.is_some_and(|p| line > p + 1) {
+                out.push_str("...\n");
+            }
+            previous = Some(line);
+
+            let text = lines[line - 1];
+            out.push_str(&format!("{:>width$} | {}\n", line, text, width = width));
+            for (span, message, mark) in &labels {
+                let start_line = line_number(span.start.line);
+                let end_line = line_number(span.end.line);
+                if line < start_line || line > end_line {
+                    continue;
+                }
+                let length = text.chars().count() + 1;
+                let start = if line == start_line { column_index(span.start.column) } else { 0 };
+                let end = if line == end_line { column_index(span.end.column) } else { length };
+                let marks = end.saturating_sub(start).max(1);
+
+                // Copy tabs so the underline lines up however they are shown.
+                let indent: String = text
+                    .chars()
+                    .chain(std::iter::repeat(' '))
+                    .take(start)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                let mut underline =
+                    format!("{} | {}{}", pad, indent, mark.to_string().repeat(marks));
+                if line == end_line && !message.is_empty() {
+                    underline.push(' ');
+                    underline.push_str(message);
+                }
+                out.push_str(&underline);
+                out.push('\n');
+            }
+        }
+
+        if let Some(help) = &self.help {
+            out.push_str(&format!("{} |\n", pad));
+            out.push_str(&format!("{} = help: {}\n", pad, help));
+        }
+        out
+    }
+}
+
+fn line_number(line: i64) -> usize {
+    line.max(1) as usize
+}
+
+fn column_index(column: i64) -> usize {
+    (column.max(1) - 1) as usize
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {} at line {}, column {}",
+            self.severity, self.code, self.message, self.span.start.line, self.span.start.column
+        )?;
+        if let Some(help) = &self.help {
+            write!(f, "; {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
@@ -0,0 +1,234 @@
+//! A small Wadler-style pretty-printing document.
+//!
+//! A [`Doc`] describes text with optional line breaks. Each [`Doc::Group`] is
+//! printed on one line if it fits in the remaining width, and otherwise has
+//! all of its own [`Doc::Line`]s broken.
+
+/// A document to be laid out by [`print`].
+#[derive(Clone, Debug)]
+pub enum Doc {
+    /// Text printed as is. Only verbatim source such as multi-line strings
+    /// contains newlines.
+    Text(String),
+    /// A space, or a newline when the enclosing group is broken.
+    Line,
+    /// Nothing, or a newline when the enclosing group is broken.
+    SoftLine,
+    /// Always a newline; the enclosing groups are broken.
+    HardLine,
+    /// Starts a new line unless nothing but indentation precedes it on the
+    /// current one. Used before comments that must sit on a line of their own.
+    OwnLine,
+    /// Prints nothing, but whatever follows starts on a new line; the
+    /// enclosing groups are broken. Used after `#` comments.
+    EndOfLine,
+    /// A `#` comment ending the line its text follows, even when a line
+    /// break has already been printed after that text. Whatever follows starts
+    /// on a new line, as after [`Doc::EndOfLine`].
+    TrailingComment(String),
+    Concat(Vec<Doc>),
+    /// Indents the lines broken inside it by one level.
+    Nest(Box<Doc>),
+    Group(Box<Doc>),
+    /// The first document when the enclosing group is broken, otherwise the
+    /// second.
+    IfBreak(Box<Doc>, Box<Doc>),
+}
+
+impl Doc {
+    pub fn nil() -> Self {
+        Doc::Concat(Vec::new())
+    }
+
+    pub fn text(text: impl Into<String>) -> Self {
+        Doc::Text(text.into())
+    }
+
+    pub fn nest(doc: Doc) -> Self {
+        Doc::Nest(Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Self {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub fn if_break(broken: Doc, flat: Doc) -> Self {
+        Doc::IfBreak(Box::new(broken), Box::new(flat))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Lays out `doc` in lines of at most `width` characters where possible,
+/// indenting by `indent` spaces per level.
+pub fn print(doc: &Doc, width: usize, indent: usize) -> String {
+    print_at(doc, width, indent, 0, 0)
+}
+
+/// Like [`print`], for a document that goes at `column` of a line indented by
+/// `level` spaces: broken lines start at `level`, and the first line has
+/// `width - column` characters left.
+pub fn print_at(doc: &Doc, width: usize, indent: usize, level: usize, column: usize) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        column,
+        pending_newline: false,
+        comment_on_line: false,
+        comment_on_previous_line: false,
+    };
+    let mut stack = vec![(level, Mode::Break, doc)];
+    while let Some((level, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => printer.text(text, level),
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat && !printer.pending_newline => {
+                if matches!(doc, Doc::Line) {
+                    printer.text(" ", level);
+                }
+            }
+            Doc::Line | Doc::SoftLine | Doc::HardLine => printer.newline(level),
+            Doc::OwnLine => {
+                if !printer.at_line_start() {
+                    printer.newline(level);
+                }
+            }
+            Doc::EndOfLine => printer.pending_newline = true,
+            Doc::TrailingComment(text) => printer.trailing_comment(text),
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (level, mode, doc))),
+            Doc::Nest(inner) => stack.push((level + indent, mode, inner)),
+            Doc::Group(inner) => {
+                let mode = if mode == Mode::Flat
+                    || fits(width.saturating_sub(printer.column), inner, &stack)
+                {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                stack.push((level, mode, inner));
+            }
+            Doc::IfBreak(broken, flat) => {
+                stack.push((level, mode, if mode == Mode::Break { broken } else { flat }));
+            }
+        }
+    }
+    printer.out
+}
+
+struct Printer {
+    out: String,
+    column: usize,
+    /// Set after a `#` comment: the next text has to go on a new line.
+    pending_newline: bool,
+    /// Whether a `#` comment runs to the end of the current line, and of the
+    /// one before it.
+    comment_on_line: bool,
+    comment_on_previous_line: bool,
+}
+
+impl Printer {
+    fn text(&mut self, text: &str, level: usize) {
+        if text.is_empty() {
+            return;
+        }
+        let text = if self.pending_newline {
+            self.newline(level);
+            text.trim_start_matches(' ')
+        } else {
+            text
+        };
+        // No code token starts with `#`, so such text is a comment.
+        self.comment_on_line |= text.starts_with('#');
+        self.out.push_str(text);
+        match text.rfind('\n') {
+            Some(index) => self.column = text[index + 1..].chars().count(),
+            None => self.column += text.chars().count(),
+        }
+    }
+
+    fn newline(&mut self, level: usize) {
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        self.out.push('\n');
+        self.out.extend(std::iter::repeat_n(' ', level));
+        self.column = level;
+        self.pending_newline = false;
+        self.comment_on_previous_line = self.comment_on_line;
+        self.comment_on_line = false;
+    }
+
+    fn trailing_comment(&mut self, text: &str) {
+        let line_start = self.out.rfind('\n').map_or(0, |index| index + 1);
+        let moves_back = line_start > 0
+            && self.out[line_start..].trim().is_empty()
+            && !self.comment_on_previous_line;
+        if !moves_back {
+            let trimmed = self.out.trim_end_matches(' ').len();
+            self.out.truncate(trimmed);
+            self.out.push(' ');
+            self.out.push_str(text);
+            self.column = self.out[line_start..].chars().count();
+            self.comment_on_line = true;
+            self.pending_newline = true;
+            return;
+        }
+        // A break was printed after the text the comment follows: the comment
+        // goes before it, and the next line keeps its indentation.
+        let indentation = self.out.split_off(line_start);
+        self.out.pop();
+        self.out.push(' ');
+        self.out.push_str(text);
+        self.out.push('\n');
+        self.out.push_str(&indentation);
+        self.comment_on_previous_line = true;
+    }
+
+    fn at_line_start(&self) -> bool {
+        let line = self.out.rsplit('\n').next().unwrap_or_default();
+        line.chars().all(|c| c == ' ') && !self.pending_newline
+    }
+}
+
+/// Whether `doc` fits flat in `width` columns, together with whatever follows
+/// it up to the next line break.
+fn fits(mut width: usize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut rest = rest.iter().rev().map(|&(_, mode, doc)| (mode, doc));
+    let mut stack = vec![(Mode::Flat, doc)];
+    loop {
+        let Some((mode, doc)) = stack.pop().or_else(|| rest.next()) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(text) => {
+                let first_line = text.split('\n').next().unwrap_or_default();
+                let length = first_line.chars().count();
+                if length > width {
+                    return false;
+                }
+                width -= length;
+                if first_line.len() < text.len() {
+                    return mode == Mode::Break;
+                }
+            }
+            Doc::Line if mode == Mode::Flat => {
+                if width == 0 {
+                    return false;
+                }
+                width -= 1;
+            }
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine => return true,
+            Doc::HardLine | Doc::EndOfLine | Doc::TrailingComment(_) => {
+                return mode == Mode::Break;
+            }
+            Doc::OwnLine => {}
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (mode, doc))),
+            Doc::Nest(inner) | Doc::Group(inner) => stack.push((mode, inner)),
+            Doc::IfBreak(broken, flat) => {
+                stack.push((mode, if mode == Mode::Break { broken } else { flat }));
+            }
+        }
+    }
+}
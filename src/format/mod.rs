@@ -0,0 +1,602 @@
+//! The Nix source formatter behind `nixlens fmt`.
+//!
+//! Expressions are turned into a [`Doc`] and laid out to fit the configured
+//! width. Formatting only moves whitespace and comments around, so the
+//! output always parses back to an equal tree, and formatting it again
+//! changes nothing.
+
+pub mod doc;
+pub mod range;
+
+use crate::diagnostic::Diagnostic;
+use crate::parser::ast::core::operators::{Associativity, precedence};
+use crate::parser::ast::core::{Comment, Position, Span, Trivia};
+use crate::parser::ast::{
+    AttrPath, BinaryOperation, Expression, FunctionHead, FunctionHeadDestructured, RenderError,
+};
+use crate::parser::parser::parse;
+
+pub use doc::Doc;
+pub use range::{format_on_type, format_range};
+
+/// Whether the last argument of a function pattern gets a comma.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TrailingComma {
+    /// `{ a, b, }`, and the same when broken over several lines.
+    Always,
+    /// `{ a, b }`, and no comma after `b` when broken either.
+    Never,
+    /// Only when the pattern is broken over several lines.
+    #[default]
+    Vertical,
+}
+
+#[derive(Clone, Debug)]
+pub struct FormatOptions {
+    /// The line width to fit in where possible.
+    pub width: usize,
+    /// Spaces per indentation level.
+    pub indent: usize,
+    pub trailing_comma: TrailingComma,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self { width: 100, indent: 2, trailing_comma: TrailingComma::default() }
+    }
+}
+
+/// Formats a whole file.
+pub fn format(source: &str, options: &FormatOptions) -> Result<String, Box<Diagnostic>> {
+    let expressions = parse(source)?;
+    Formatter { options, source: Some(source) }
+        .file(&expressions)
+        .map_err(|error| error.to_diagnostic(expressions[0].span()).into())
+}
+
+/// Formats expressions as a file, ending with a newline. Fails on `Error`
+/// nodes.
+pub fn format_expressions(
+    expressions: &[Expression],
+    options: &FormatOptions,
+) -> Result<String, RenderError> {
+    Formatter { options, source: None }.file(expressions)
+}
+
+/// Formats a single expression without a final newline, e.g. to insert it
+/// into existing code.
+pub fn format_expression(
+    expression: &Expression,
+    options: &FormatOptions,
+) -> Result<String, RenderError> {
+    let doc = Formatter { options, source: None }.expression(expression)?;
+    Ok(doc::print(&doc, options.width, options.indent))
+}
+
+struct Formatter<'a> {
+    options: &'a FormatOptions,
+    /// The text the expressions were parsed from, if any.
+    source: Option<&'a str>,
+}
+
+type DocResult = Result<Doc, RenderError>;
+
+impl Formatter<'_> {
+    fn file(&self, expressions: &[Expression]) -> Result<String, RenderError> {
+        let mut docs = Vec::new();
+        for (index, expression) in expressions.iter().enumerate() {
+            if index > 0 {
+                docs.push(Doc::HardLine);
+            }
+            docs.push(self.expression(expression)?);
+        }
+        let options = self.options;
+        let mut formatted = doc::print(&Doc::Concat(docs), options.width, options.indent);
+        formatted.truncate(formatted.trim_end().len());
+        formatted.push('\n');
+        Ok(formatted)
+    }
+
+    fn expression(&self, expression: &Expression) -> DocResult {
+        let doc = self.node(expression)?;
+        Ok(self.with_trivia(expression, doc))
+    }
+
+    /// An expression in a position that takes at most precedence `max`
+    /// without parentheses. Its comments go outside the parentheses, where
+    /// they were found.
+    fn operand(&self, expression: &Expression, max: u8) -> DocResult {
+        if expression.precedence() <= max {
+            return self.expression(expression);
+        }
+        let doc = Doc::group(Doc::Concat(vec![
+            Doc::text("("),
+            Doc::nest(Doc::Concat(vec![Doc::SoftLine, self.node(expression)?])),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ]));
+        Ok(self.with_trivia(expression, doc))
+    }
+
+    /// An expression following something like the `=` of a binding: on the
+    /// same line if it opens a bracket of its own, otherwise on the next line
+    /// and indented when it does not fit.
+    fn hanging(&self, expression: &Expression) -> DocResult {
+        let doc = self.expression(expression)?;
+        if hugs(expression) {
+            return Ok(Doc::Concat(vec![Doc::text(" "), doc]));
+        }
+        Ok(Doc::group(Doc::nest(Doc::Concat(vec![Doc::Line, doc]))))
+    }
+
+    /// Bracketed items, one per line when they do not fit on one. `comments`
+    /// are those written between the brackets when there are no items.
+    fn block(
+        &self,
+        open: &str,
+        items: &[Expression],
+        close: &str,
+        max: u8,
+        comments: &[Comment],
+    ) -> DocResult {
+        if items.is_empty() && comments.is_empty() {
+            return Ok(Doc::text(format!("{} {}", open, close)));
+        }
+        let mut inner = Vec::new();
+        for comment in comments {
+            inner.push(Doc::Line);
+            inner.push(Doc::text(comment.text.clone()));
+            if comment.is_line() {
+                inner.push(Doc::EndOfLine);
+            }
+        }
+        for (index, item) in items.iter().enumerate() {
+            inner.push(match index {
+                0 => Doc::Line,
+                _ => self.separator(&items[index - 1], item),
+            });
+            inner.push(self.operand(item, max)?);
+        }
+        Ok(Doc::group(Doc::Concat(vec![
+            Doc::text(open),
+            Doc::nest(Doc::Concat(inner)),
+            Doc::Line,
+            Doc::text(close),
+        ])))
+    }
+
+    fn node(&self, expression: &Expression) -> DocResult {
+        Ok(match expression {
+            Expression::Integer(x) => Doc::text(x.render()),
+            Expression::Float(x) => Doc::text(x.render()),
+            Expression::Identifier(x) => Doc::text(x.render()),
+            Expression::LiteralString(x) => Doc::text(x.render()),
+            Expression::Boolean(x) => Doc::text(x.render()),
+            Expression::Null(x) => Doc::text(x.render()),
+            Expression::SearchNixPath(x) => Doc::text(x.render()),
+            Expression::Uri(x) => Doc::text(x.render()),
+            // Strings and paths are kept verbatim, interpolations included.
+            Expression::Path(x) => Doc::text(x.render()?),
+            Expression::NixString(x) => Doc::text(x.render()?),
+            Expression::IndentedString(x) => Doc::text(x.render()?),
+            Expression::PartRaw(x) => Doc::text(x.render()),
+            Expression::PartInterpolation(x) => Doc::text(x.render()?),
+            Expression::UnaryOperation(x) => Doc::Concat(vec![
+                Doc::text(x.operator.render()),
+                self.operand(&x.expression, x.operator.precedence())?,
+            ]),
+            Expression::BinaryOperation(x) => self.binary(x)?,
+            Expression::List(x) => {
+                self.block("[", &x.elements, "]", precedence::SELECT, &x.trivia.inner)?
+            }
+            Expression::AttrSet(x) => {
+                let comments = &x.trivia.inner;
+                let set = self.block("{", &x.bindings, "}", precedence::LOWEST, comments)?;
+                if x.recursive {
+                    Doc::Concat(vec![Doc::text("rec "), set])
+                } else {
+                    set
+                }
+            }
+            Expression::IfThenElse(x) => {
+                // `else if` chains stay flat rather than nesting ever deeper.
+                let else_ = match x.else_.as_ref() {
+                    Expression::IfThenElse(_) if x.else_.trivia().leading.is_empty() => {
+                        Doc::Concat(vec![Doc::text(" "), self.expression(&x.else_)?])
+                    }
+                    _ => Doc::nest(Doc::Concat(vec![Doc::Line, self.expression(&x.else_)?])),
+                };
+                Doc::group(Doc::Concat(vec![
+                    Doc::text("if "),
+                    self.expression(&x.predicate)?,
+                    Doc::text(" then"),
+                    Doc::nest(Doc::Concat(vec![Doc::Line, self.expression(&x.then)?])),
+                    Doc::Line,
+                    Doc::text("else"),
+                    else_,
+                ]))
+            }
+            Expression::Function(x) => {
+                let body = match x.body.as_ref() {
+                    body if hugs(body) || matches!(body, Expression::Function(_)) => {
+                        Doc::Concat(vec![Doc::text(" "), self.expression(body)?])
+                    }
+                    body if self.is_blank_line_between(&x.head.span().end, body) => {
+                        Doc::Concat(vec![Doc::HardLine, Doc::HardLine, self.expression(body)?])
+                    }
+                    body => Doc::Concat(vec![Doc::Line, self.expression(body)?]),
+                };
+                Doc::group(Doc::Concat(vec![self.function_head(&x.head)?, Doc::text(":"), body]))
+            }
+            Expression::FunctionApplication(x) => {
+                let mut head = vec![self.operand(&x.function, precedence::SELECT)?];
+                let (last, arguments) =
+                    x.arguments.split_last().expect("applications have arguments");
+                for argument in arguments {
+                    head.push(Doc::Line);
+                    head.push(self.operand(argument, precedence::SELECT)?);
+                }
+                let last_doc = self.operand(last, precedence::SELECT)?;
+                // A trailing attribute set or list opens on the same line, as
+                // in `mkDerivation {`, and only breaks inside itself.
+                if hugs(last) {
+                    Doc::Concat(vec![
+                        Doc::group(Doc::nest(Doc::Concat(head))),
+                        Doc::text(" "),
+                        last_doc,
+                    ])
+                } else {
+                    head.push(Doc::Line);
+                    head.push(last_doc);
+                    Doc::group(Doc::nest(Doc::Concat(head)))
+                }
+            }
+            Expression::PropertyAccess(x) => {
+                let mut parts = vec![
+                    self.operand(&x.expression, precedence::ATOM)?,
+                    Doc::text("."),
+                    self.attr_path(&x.attribute_path)?,
+                ];
+                if let Some(default) = &x.default {
+                    parts.push(Doc::text(" or "));
+                    parts.push(self.operand(default, precedence::SELECT)?);
+                }
+                Doc::Concat(parts)
+            }
+            Expression::HasAttribute(x) => Doc::Concat(vec![
+                self.operand(&x.expression, precedence::HAS_ATTRIBUTE - 1)?,
+                Doc::text(" ? "),
+                self.attr_path(&x.attribute_path)?,
+            ]),
+            Expression::BindingInherit(x) => {
+                let mut parts = vec![Doc::text("inherit")];
+                if let Some(from) = &x.from_ {
+                    parts.push(Doc::text(" ("));
+                    parts.push(self.expression(from)?);
+                    parts.push(Doc::text(")"));
+                }
+                for attribute in &x.attributes {
+                    parts.push(Doc::Line);
+                    parts.push(self.expression(attribute)?);
+                }
+                parts.push(Doc::text(";"));
+                Doc::group(Doc::nest(Doc::Concat(parts)))
+            }
+            Expression::BindingKeyValue(x) => Doc::Concat(vec![
+                self.attr_path(&x.from_)?,
+                Doc::text(" ="),
+                self.hanging(&x.to)?,
+                Doc::text(";"),
+            ]),
+            Expression::With(x) => {
+                let body = self.expression(&x.body)?;
+                Doc::group(Doc::Concat(vec![
+                    Doc::text("with "),
+                    self.expression(&x.scope)?,
+                    Doc::text(";"),
+                    if hugs(&x.body) { Doc::text(" ") } else { Doc::Line },
+                    body,
+                ]))
+            }
+            Expression::LetIn(x) => {
+                let bindings = if x.bindings.is_empty() {
+                    Doc::text(" ")
+                } else {
+                    let mut inner = Vec::new();
+                    for (index, binding) in x.bindings.iter().enumerate() {
+                        inner.push(match index {
+                            0 => Doc::Line,
+                            _ => self.separator(&x.bindings[index - 1], binding),
+                        });
+                        inner.push(self.expression(binding)?);
+                    }
+                    Doc::Concat(vec![Doc::nest(Doc::Concat(inner)), Doc::Line])
+                };
+                Doc::group(Doc::Concat(vec![
+                    Doc::text("let"),
+                    bindings,
+                    Doc::text("in"),
+                    Doc::Line,
+                    self.expression(&x.target)?,
+                ]))
+            }
+            Expression::Assert(x) => Doc::group(Doc::Concat(vec![
+                Doc::text("assert "),
+                self.expression(&x.condition)?,
+                Doc::text(";"),
+                Doc::Line,
+                self.expression(&x.body)?,
+            ])),
+            Expression::Throw(x) => Doc::Concat(vec![
+                Doc::text("throw "),
+                self.operand(&x.message, precedence::SELECT)?,
+            ]),
+            Expression::Error(x) => {
+                return Err(RenderError::InvalidNode(x.diagnostic.message.clone()));
+            }
+        })
+    }
+
+    /// A chain of operators of the same precedence, such as `a + b - c`, is
+    /// laid out as one group with a break before each operator.
+    fn binary(&self, operation: &BinaryOperation) -> DocResult {
+        let precedence = operation.operator.precedence();
+        let associativity = operation.operator.associativity();
+        // The first operand, then each further operand with the operator
+        // written before it.
+        let mut links = Vec::new();
+        let mut current = operation;
+        let first = match associativity {
+            Associativity::Left => loop {
+                links.push((&current.operator, current.right.as_ref()));
+                match chained(&current.left, precedence) {
+                    Some(left) => current = left,
+                    None => break current.left.as_ref(),
+                }
+            },
+            Associativity::Right => loop {
+                match chained(&current.right, precedence) {
+                    Some(right) => {
+                        links.push((&current.operator, right.left.as_ref()));
+                        current = right;
+                    }
+                    None => {
+                        links.push((&current.operator, current.right.as_ref()));
+                        break operation.left.as_ref();
+                    }
+                }
+            },
+            Associativity::None => {
+                links.push((&current.operator, current.right.as_ref()));
+                current.left.as_ref()
+            }
+        };
+        if associativity == Associativity::Left {
+            links.reverse();
+        }
+
+        // Only the operand on the side the chain groups towards can share the
+        // operator's precedence without parentheses.
+        let first_max = match associativity {
+            Associativity::Left => precedence,
+            _ => precedence - 1,
+        };
+        let mut parts = vec![self.operand(first, first_max)?];
+        let count = links.len();
+        for (index, (operator, operand)) in links.into_iter().enumerate() {
+            let max = match associativity {
+                Associativity::Right if index + 1 == count => precedence,
+                _ => precedence - 1,
+            };
+            parts.push(Doc::Line);
+            parts.push(Doc::text(format!("{} ", operator.render())));
+            parts.push(self.operand(operand, max)?);
+        }
+        Ok(Doc::group(Doc::Concat(parts)))
+    }
+
+    /// Wraps `doc`, the layout of `expression`, with the expression's
+    /// comments. A comment stays on a line of its own if it was written on
+    /// one, and next to the node otherwise.
+    fn with_trivia(&self, expression: &Expression, doc: Doc) -> Doc {
+        let trivia = expression.trivia();
+        if trivia.is_empty() {
+            return doc;
+        }
+        let span = expression.span();
+        let mut parts = self.leading_comments(trivia, &span);
+        parts.push(doc);
+        parts.extend(self.trailing_comments(trivia, &span));
+        Doc::Concat(parts)
+    }
+
+    /// The comments before a node spanning `span`.
+    fn leading_comments(&self, trivia: &Trivia, span: &Span) -> Vec<Doc> {
+        let mut parts = Vec::new();
+        for comment in &trivia.leading {
+            let around = self.line_around(&comment.span);
+            if comment.is_line() && around.is_some_and(|(before, _)| !before.trim().is_empty()) {
+                // Written after a token such as the `;` of `assert a; # c`.
+                parts.push(Doc::TrailingComment(comment.text.clone()));
+                continue;
+            }
+            let alone = around.map_or(comment.span.end.line < span.start.line, |(_, after)| {
+                after.trim().is_empty()
+            });
+            if comment.is_line() || alone {
+                parts.push(Doc::OwnLine);
+                parts.push(Doc::text(comment.text.clone()));
+                parts.push(Doc::HardLine);
+            } else {
+                parts.push(Doc::text(format!("{} ", comment.text)));
+            }
+        }
+        parts
+    }
+
+    /// The comments after a node spanning `span`.
+    fn trailing_comments(&self, trivia: &Trivia, span: &Span) -> Vec<Doc> {
+        let mut parts = Vec::new();
+        for comment in &trivia.trailing {
+            // Parentheses around the node lie outside its span, so the
+            // source tells better than the span where a comment was.
+            let alone = self.line_around(&comment.span).map_or(
+                comment.span.start.line > span.end.line,
+                |(before, _)| before.trim().is_empty(),
+            );
+            if alone {
+                parts.push(Doc::HardLine);
+                parts.push(Doc::text(comment.text.clone()));
+            } else {
+                parts.push(Doc::text(format!(" {}", comment.text)));
+            }
+            if comment.is_line() {
+                parts.push(Doc::EndOfLine);
+            }
+        }
+        parts
+    }
+
+    /// The source text before and after `span` on the lines it starts and
+    /// ends on, if the source is known.
+    fn line_around(&self, span: &Span) -> Option<(&str, &str)> {
+        let source = self.source?;
+        if span.is_detached() {
+            return None;
+        }
+        let before = source.get(..span.start.offset)?;
+        let after = source.get(span.end.offset..)?;
+        let before = &before[before.rfind('\n').map_or(0, |index| index + 1)..];
+        let after = &after[..after.find('\n').unwrap_or(after.len())];
+        Some((before, after))
+    }
+
+    /// The break between two items of a block, keeping one blank line where
+    /// the source had any.
+    fn separator(&self, previous: &Expression, next: &Expression) -> Doc {
+        let trailing = &previous.trivia().trailing;
+        let end = trailing.last().map_or(previous.span().end, |comment| comment.span.end.clone());
+        if self.is_blank_line_between(&end, next) {
+            Doc::Concat(vec![Doc::HardLine, Doc::HardLine])
+        } else {
+            Doc::Line
+        }
+    }
+
+    /// Whether the source had an empty line between `end` and `next`. Nodes
+    /// built in code have no lines and never do.
+    fn is_blank_line_between(&self, end: &Position, next: &Expression) -> bool {
+        let start = next.trivia().leading.first().map_or(next.span().start, |comment| {
+            comment.span.start.clone()
+        });
+        if end.line == 0 {
+            return false;
+        }
+        // Parentheses belong to no span, so `(` on a line of its own looks
+        // like an empty line unless the text itself is checked.
+        match self.source.and_then(|source| source.get(end.offset..start.offset)) {
+            Some(gap) => {
+                let lines: Vec<&str> = gap.split('\n').collect();
+                lines.len() > 2
+                    && lines[1..lines.len() - 1].iter().any(|line| line.trim().is_empty())
+            }
+            None => start.line > end.line + 1,
+        }
+    }
+
+    fn attr_path(&self, path: &AttrPath) -> DocResult {
+        let mut parts = Vec::new();
+        for (index, attribute) in path.attributes.iter().enumerate() {
+            if index > 0 {
+                parts.push(Doc::text("."));
+            }
+            parts.push(self.expression(attribute)?);
+        }
+        Ok(Doc::Concat(parts))
+    }
+
+    fn function_head(&self, head: &FunctionHead) -> DocResult {
+        match head {
+            FunctionHead::FunctionHeadSimple(x) => Ok(Doc::text(x.render())),
+            FunctionHead::FunctionHeadDestructured(x) => {
+                let pattern = self.pattern(x)?;
+                Ok(match &x.identifier {
+                    Some(identifier) if x.identifier_before => {
+                        Doc::Concat(vec![Doc::text(format!("{}@", identifier.render())), pattern])
+                    }
+                    Some(identifier) => {
+                        Doc::Concat(vec![pattern, Doc::text(format!("@{}", identifier.render()))])
+                    }
+                    None => pattern,
+                })
+            }
+        }
+    }
+
+    fn pattern(&self, head: &FunctionHeadDestructured) -> DocResult {
+        if head.arguments.is_empty() && !head.ellipsis {
+            return Ok(Doc::text("{ }"));
+        }
+        let mut inner = Vec::new();
+        for (index, argument) in head.arguments.iter().enumerate() {
+            inner.push(Doc::Line);
+            inner.extend(self.leading_comments(&argument.trivia, &argument.span));
+            inner.push(Doc::text(argument.identifier.render()));
+            if let Some(default) = &argument.default {
+                inner.push(Doc::text(" ?"));
+                inner.push(self.hanging(default)?);
+            }
+            // The comma goes before the comments after the formal, which may
+            // run to the end of the line.
+            let last = index + 1 == head.arguments.len() && !head.ellipsis;
+            inner.push(match self.options.trailing_comma {
+                _ if !last => Doc::text(","),
+                TrailingComma::Always => Doc::text(","),
+                TrailingComma::Never => Doc::nil(),
+                TrailingComma::Vertical => Doc::if_break(Doc::text(","), Doc::nil()),
+            });
+            inner.extend(self.trailing_comments(&argument.trivia, &argument.span));
+        }
+        // `...` has to come last, so it never takes a comma.
+        if head.ellipsis {
+            inner.push(Doc::Line);
+            inner.push(Doc::text("..."));
+        }
+        Ok(Doc::group(Doc::Concat(vec![
+            Doc::text("{"),
+            Doc::nest(Doc::Concat(inner)),
+            Doc::Line,
+            Doc::text("}"),
+        ])))
+    }
+}
+
+/// The operation `expression` if it continues a chain of operators of
+/// `precedence`. Operations with comments of their own are kept whole.
+fn chained(expression: &Expression, precedence: u8) -> Option<&BinaryOperation> {
+    match expression {
+        Expression::BinaryOperation(x)
+            if x.operator.precedence() == precedence && x.trivia.is_empty() =>
+        {
+            Some(x)
+        }
+        _ => None,
+    }
+}
+
+/// Whether an expression opens with a bracket of its own and can start on the
+/// line of whatever precedes it, as in `a = {` or `x: [`.
+fn hugs(expression: &Expression) -> bool {
+    if !expression.trivia().leading.is_empty() {
+        return false;
+    }
+    match expression {
+        Expression::AttrSet(_)
+        | Expression::List(_)
+        | Expression::NixString(_)
+        | Expression::IndentedString(_) => true,
+        Expression::FunctionApplication(x) => x.arguments.last().is_some_and(hugs),
+        Expression::With(x) => hugs(&x.body),
+        _ => false,
+    }
+}
@@ -0,0 +1,230 @@
+//! Formatting part of a file, for editors.
+//!
+//! Rather than a whole new file, these return the [`TextEdit`]s that turn the
+//! source into its formatted form, touching only the nodes asked for and only
+//! the characters that change inside them. Hand-formatted code elsewhere is
+//! left alone, and so are nodes the parser could not make sense of.
+
+use std::ops::Range;
+
+use crate::parser::ast::core::Span;
+use crate::parser::ast::{Expression, node_at};
+use crate::parser::cst::TextEdit;
+use crate::parser::parser::parse_recovering;
+
+use super::{FormatOptions, Formatter, doc};
+
+/// Above this many token pairs the changes inside a node are not worked out
+/// one by one, and the node is replaced as a whole.
+const DIFF_LIMIT: usize = 1 << 22;
+
+/// Formats the nodes covering the byte `range` of `source`.
+///
+/// The range is widened to whole nodes that stand on their own: bindings, list
+/// elements and top-level expressions. A range within one of them formats the
+/// innermost such node around it; a range across several formats each of
+/// them, and not the whitespace between them. Nodes containing syntax errors
+/// are left as they are. A range ending past the end of `source` is cut
+/// short there; one that is reversed or starts past the end formats nothing.
+pub fn format_range(source: &str, range: Range<usize>, options: &FormatOptions) -> Vec<TextEdit> {
+    if range.start > range.end || range.start > source.len() {
+        return Vec::new();
+    }
+    let range = range.start..range.end.min(source.len());
+    let parsed = parse_recovering(source);
+    units(&parsed.expressions, &range)
+        .into_iter()
+        .flat_map(|unit| format_node(source, unit, options))
+        .collect()
+}
+
+/// Formats the binding completed by typing `typed` just before the byte
+/// `offset`. Only `;` triggers formatting; any other character gives no edits.
+pub fn format_on_type(
+    source: &str,
+    offset: usize,
+    typed: char,
+    options: &FormatOptions,
+) -> Vec<TextEdit> {
+    if typed != ';' || offset == 0 || offset > source.len() {
+        return Vec::new();
+    }
+    let parsed = parse_recovering(source);
+    let Some(path) = node_at(&parsed.expressions, offset - 1) else {
+        return Vec::new();
+    };
+    let binding = path.iter().find(|node| {
+        matches!(node, Expression::BindingKeyValue(_) | Expression::BindingInherit(_))
+            && node.span().end.offset == offset
+    });
+    match binding {
+        Some(binding) => format_node(source, binding, options),
+        None => Vec::new(),
+    }
+}
+
+/// The standalone nodes among `items` to format for `range`, looking into
+/// nested blocks when the range lies inside a single item.
+fn units<'a>(items: &'a [Expression], range: &Range<usize>) -> Vec<&'a Expression> {
+    let overlapping: Vec<&Expression> =
+        items.iter().filter(|item| overlaps(item.span(), range)).collect();
+    if let [item] = overlapping[..]
+        && contains(item.span(), range)
+        && let Some(nested) = nested_units(item, range)
+    {
+        return nested;
+    }
+    overlapping
+}
+
+fn nested_units<'a>(node: &'a Expression, range: &Range<usize>) -> Option<Vec<&'a Expression>> {
+    let items = match node {
+        Expression::List(x) => &x.elements[..],
+        Expression::AttrSet(x) => &x.bindings[..],
+        Expression::LetIn(x) => &x.bindings[..],
+        _ => &[],
+    };
+    // Items are formatted on their own unless the range reaches the brackets
+    // or keywords around them.
+    let span = node.span();
+    let inside = match node {
+        Expression::LetIn(_) => {
+            span.start.offset + "let".len() <= range.start
+                && items.last().is_some_and(|last| range.end <= last.span().end.offset)
+        }
+        _ => span.start.offset < range.start && range.end < span.end.offset,
+    };
+    if inside {
+        let units = units(items, range);
+        if !units.is_empty() {
+            return Some(units);
+        }
+    }
+    node.children()
+        .into_iter()
+        .find(|child| contains(child.span(), range))
+        .and_then(|child| nested_units(child, range))
+}
+
+/// Whether a node at `span` overlaps `range`, or touches it if the range is
+/// empty.
+fn overlaps(span: Span, range: &Range<usize>) -> bool {
+    if range.is_empty() {
+        return contains(span, range);
+    }
+    !span.is_detached() && span.start.offset < range.end && range.start < span.end.offset
+}
+
+fn contains(span: Span, range: &Range<usize>) -> bool {
+    !span.is_detached() && span.start.offset <= range.start && range.end <= span.end.offset
+}
+
+/// The edits formatting `node` where it stands in `source`.
+fn format_node(source: &str, node: &Expression, options: &FormatOptions) -> Vec<TextEdit> {
+    let span = node.span();
+    let (start, end) = (span.start.offset, span.end.offset);
+    // The node's own comments lie outside its span and stay where they are.
+    let Ok(doc) = Formatter { options, source: Some(source) }.node(node) else {
+        return Vec::new();
+    };
+
+    let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+    let line = &source[line_start..start];
+    let level = line.len() - line.trim_start().len();
+    let column = line.chars().count();
+    let formatted = doc::print_at(&doc, options.width, options.indent, level, column);
+    diff(&source[start..end], &formatted, start)
+}
+
+/// Splits text into runs of whitespace, runs of word characters, and single
+/// other characters.
+fn tokens(text: &str) -> Vec<&str> {
+    let class = |c: char| match c {
+        c if c.is_whitespace() => 0,
+        c if c.is_alphanumeric() || c == '_' => 1,
+        _ => 2,
+    };
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let end = match chars.peek() {
+            Some(&(next, d)) if class(c) == 2 || class(c) != class(d) => next,
+            Some(_) => continue,
+            None => text.len(),
+        };
+        tokens.push(&text[start..end]);
+        start = end;
+    }
+    tokens
+}
+
+/// The edits turning `old`, found at byte `offset` of the source, into `new`:
+/// each run of tokens the two do not share is replaced on its own.
+fn diff(old: &str, new: &str, offset: usize) -> Vec<TextEdit> {
+    if old == new {
+        return Vec::new();
+    }
+    let (a, b) = (tokens(old), tokens(new));
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_middle, b_middle) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    // Pairs of indices into the middles of tokens kept unchanged, in order.
+    let kept = if a_middle.len().saturating_mul(b_middle.len()) > DIFF_LIMIT {
+        Vec::new()
+    } else {
+        common_subsequence(a_middle, b_middle)
+    };
+
+    let mut edits = Vec::new();
+    let mut old_offset = offset + a[..prefix].iter().map(|token| token.len()).sum::<usize>();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in kept.into_iter().chain([(a_middle.len(), b_middle.len())]) {
+        let removed: usize = a_middle[i..next_i].iter().map(|token| token.len()).sum();
+        if next_i > i || next_j > j {
+            edits.push(TextEdit::new(
+                old_offset..old_offset + removed,
+                b_middle[j..next_j].concat(),
+            ));
+        }
+        old_offset += removed + a_middle.get(next_i).map_or(0, |token| token.len());
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+    edits
+}
+
+/// A longest common subsequence of `a` and `b`, as index pairs.
+fn common_subsequence(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    // `lengths[i][j]` is the length of the longest common subsequence of
+    // `a[i..]` and `b[j..]`.
+    let width = b.len() + 1;
+    let mut lengths = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i * width + j] = if a[i] == b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            (i, j) = (i + 1, j + 1);
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
@@ -1 +1,3 @@
-pub mod parser;
\ No newline at end of file
+pub mod diagnostic;
+pub mod format;
+pub mod parser;
@@ -16,16 +16,133 @@
 // along with GNix.  If not, see <https://www.gnu.org/licenses/>.                           |
 // -----------------------------------------------------------------------------------------|
 
-use parser::ast::core::*;
-use parser::ast::expr::*;
-use pest::Parser;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::{env, fs};
 
-use parser::parser::{NixParser, parse_expr, parse, Rule};
+use nix_lens::format::{FormatOptions, TrailingComma, format};
 
-mod parser;
+const USAGE: &str = "\
+usage: nixlens [-h|--help] [--version] <command> [<args>]
 
-fn main() {
-    println!("GNix NixLens Parser Module");
+commands:
+    fmt     format Nix files";
 
-    print!("{:?}", parse("x + 1").unwrap());
+const FMT_USAGE: &str = "\
+usage: nixlens fmt [--check] [--width <n>] [--indent <n>]
+                   [--trailing-comma <always|never|vertical>] [<file>...]
+
+Formats the files in place, or standard input to standard output when no file
+is given. With --check nothing is written; files that are not formatted are
+listed and the exit status is 1.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("fmt") => fmt(&args[1..]),
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Some("--version") => {
+            println!("nixlens {}", env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        Some(command) => usage_error(&format!("unknown command `{}`", command), USAGE),
+        None => usage_error("no command given", USAGE),
+    }
+}
+
+fn usage_error(message: &str, usage: &str) -> ExitCode {
+    eprintln!("error: {}\n\n{}", message, usage);
+    ExitCode::from(2)
+}
+
+fn fmt(args: &[String]) -> ExitCode {
+    let mut options = FormatOptions::default();
+    let mut check = false;
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", FMT_USAGE);
+                return ExitCode::SUCCESS;
+            }
+            "--check" => check = true,
+            "--width" | "--indent" => {
+                let Some(value) = args.next().and_then(|value| value.parse().ok()) else {
+                    return usage_error(&format!("`{}` takes a number", arg), FMT_USAGE);
+                };
+                if arg == "--width" {
+                    options.width = value;
+                } else {
+                    options.indent = value;
+                }
+            }
+            "--trailing-comma" => {
+                options.trailing_comma = match args.next().map(String::as_str) {
+                    Some("always") => TrailingComma::Always,
+                    Some("never") => TrailingComma::Never,
+                    Some("vertical") => TrailingComma::Vertical,
+                    _ => {
+                        return usage_error(
+                            "`--trailing-comma` takes `always`, `never` or `vertical`",
+                            FMT_USAGE,
+                        );
+                    }
+                };
+            }
+            option if option.starts_with('-') && option != "-" => {
+                return usage_error(&format!("unknown option `{}`", option), FMT_USAGE);
+            }
+            file => files.push(file.to_string()),
+        }
+    }
+
+    if files.is_empty() {
+        files.push("-".to_string());
+    }
+    let mut status = ExitCode::SUCCESS;
+    for file in &files {
+        match fmt_file(file, &options, check) {
+            Ok(true) => {}
+            Ok(false) => {
+                println!("{}", if file == "-" { "<stdin>" } else { file });
+                status = ExitCode::FAILURE;
+            }
+            Err(message) => {
+                eprintln!("{}", message);
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+    status
+}
+
+/// Formats one file, or standard input for `-`. Returns whether it was
+/// already formatted.
+fn fmt_file(file: &str, options: &FormatOptions, check: bool) -> Result<bool, String> {
+    let stdin = file == "-";
+    let source = if stdin {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source).map_err(|error| error.to_string())?;
+        source
+    } else {
+        fs::read_to_string(file).map_err(|error| format!("error: {}: {}", file, error))?
+    };
+
+    let name = if stdin { "<stdin>" } else { file };
+    let formatted = format(&source, options).map_err(|error| error.render(&source, name))?;
+    let unchanged = formatted == source;
+    if check {
+        return Ok(unchanged);
+    }
+    if stdin {
+        io::stdout().write_all(formatted.as_bytes()).map_err(|error| error.to_string())?;
+    } else if !unchanged {
+        fs::write(file, formatted).map_err(|error| format!("error: {}: {}", file, error))?;
+    }
+    Ok(true)
 }
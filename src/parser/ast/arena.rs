@@ -0,0 +1,252 @@
+//! A flat, index-based copy of the AST.
+//!
+//! Every node lives in one `Vec` and is addressed by a [`NodeId`], so
+//! analyses can keep side tables indexed by node and walk up through parents.
+//! Nodes only hold their own data; sub-expressions are reached through
+//! [`Arena::children`], in the same order as [`Expression::children`].
+
+use std::ops::{Index, Range};
+
+use super::core::{Operator, PathKind, Span};
+use super::expr::{Expression, FunctionHead};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    /// Position of the node in the arena, for use as an index into side
+    /// tables.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A parameter of a destructuring function head.
+#[derive(Clone, Debug)]
+pub struct Formal {
+    pub name: String,
+    /// Whether the formal has a default; defaults are children of the
+    /// function node, in order, before its body.
+    pub has_default: bool,
+}
+
+/// The data of a node, without its sub-expressions.
+#[derive(Clone, Debug)]
+pub enum NodeKind {
+    Integer(i64),
+    Float(f64),
+    Identifier(String),
+    LiteralString(String),
+    Boolean(bool),
+    Null,
+    Path(PathKind),
+    SearchNixPath(String),
+    Uri(String),
+    UnaryOperation(Operator),
+    BinaryOperation(Operator),
+    List,
+    AttrSet { recursive: bool },
+    IfThenElse,
+    Function {
+        /// The `x` in `x: ...` or `x@{ ... }: ...`.
+        identifier: Option<String>,
+        /// `None` for a simple head.
+        formals: Option<Vec<Formal>>,
+        ellipsis: bool,
+    },
+    FunctionApplication,
+    /// Children are the expression, the attribute path segments and then the
+    /// `or` default, if any.
+    PropertyAccess { has_default: bool },
+    HasAttribute,
+    NixString,
+    IndentedString,
+    PartRaw(String),
+    PartInterpolation,
+    /// Children are the `(from)` expression, if any, then the attributes.
+    BindingInherit { has_from: bool },
+    /// Children are the attribute path segments, then the value.
+    BindingKeyValue,
+    With,
+    LetIn,
+    Assert,
+    Throw,
+    Error(String),
+}
+
+impl NodeKind {
+    fn from_expression(expression: &Expression) -> Self {
+        match expression {
+            Expression::Integer(x) => NodeKind::Integer(x.value),
+            Expression::Float(x) => NodeKind::Float(x.value),
+            Expression::Identifier(x) => NodeKind::Identifier(x.id.clone()),
+            Expression::LiteralString(x) => NodeKind::LiteralString(x.value.clone()),
+            Expression::Boolean(x) => NodeKind::Boolean(x.value),
+            Expression::Null(_) => NodeKind::Null,
+            Expression::Path(x) => NodeKind::Path(x.kind),
+            Expression::SearchNixPath(x) => NodeKind::SearchNixPath(x.path.clone()),
+            Expression::Uri(x) => NodeKind::Uri(x.uri.clone()),
+            Expression::UnaryOperation(x) => NodeKind::UnaryOperation(x.operator.clone()),
+            Expression::BinaryOperation(x) => NodeKind::BinaryOperation(x.operator.clone()),
+            Expression::List(_) => NodeKind::List,
+            Expression::AttrSet(x) => NodeKind::AttrSet { recursive: x.recursive },
+            Expression::IfThenElse(_) => NodeKind::IfThenElse,
+            Expression::Function(x) => match &x.head {
+                FunctionHead::FunctionHeadSimple(head) => NodeKind::Function {
+                    identifier: Some(head.identifier.id.clone()),
+                    formals: None,
+                    ellipsis: false,
+                },
+                FunctionHead::FunctionHeadDestructured(head) => NodeKind::Function {
+                    identifier: head.identifier.as_ref().map(|i| i.id.clone()),
+                    formals: Some(
+                        head.arguments
+                            .iter()
+                            .map(|a| Formal {
+                                name: a.identifier.id.clone(),
+                                has_default: a.default.is_some(),
+                            })
+                            .collect(),
+                    ),
+                    ellipsis: head.ellipsis,
+                },
+            },
+            Expression::FunctionApplication(_) => NodeKind::FunctionApplication,
+            Expression::PropertyAccess(x) => {
+                NodeKind::PropertyAccess { has_default: x.default.is_some() }
+            }
+            Expression::HasAttribute(_) => NodeKind::HasAttribute,
+            Expression::NixString(_) => NodeKind::NixString,
+            Expression::IndentedString(_) => NodeKind::IndentedString,
+            Expression::PartRaw(x) => NodeKind::PartRaw(x.content.clone()),
+            Expression::PartInterpolation(_) => NodeKind::PartInterpolation,
+            Expression::BindingInherit(x) => {
+                NodeKind::BindingInherit { has_from: x.from_.is_some() }
+            }
+            Expression::BindingKeyValue(_) => NodeKind::BindingKeyValue,
+            Expression::With(_) => NodeKind::With,
+            Expression::LetIn(_) => NodeKind::LetIn,
+            Expression::Assert(_) => NodeKind::Assert,
+            Expression::Throw(_) => NodeKind::Throw,
+            Expression::Error(x) => NodeKind::Error(x.diagnostic.message.clone()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: Span,
+    parent: Option<NodeId>,
+    /// Children are allocated next to each other, so a range is enough.
+    children: Range<u32>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Arena {
+    nodes: Vec<Node>,
+    roots: Vec<NodeId>,
+}
+
+impl Arena {
+    pub fn from_expressions(expressions: &[Expression]) -> Self {
+        let mut arena = Arena::default();
+        let roots = arena.allocate(expressions.iter(), None);
+        arena.roots = roots.clone().map(NodeId).collect();
+        for (id, expression) in roots.zip(expressions) {
+            arena.fill(NodeId(id), expression);
+        }
+        arena
+    }
+
+    pub fn from_expression(expression: &Expression) -> Self {
+        Self::from_expressions(std::slice::from_ref(expression))
+    }
+
+    fn allocate<'a>(
+        &mut self,
+        expressions: impl Iterator<Item = &'a Expression>,
+        parent: Option<NodeId>,
+    ) -> Range<u32> {
+        let start = self.nodes.len() as u32;
+        for expression in expressions {
+            self.nodes.push(Node {
+                kind: NodeKind::from_expression(expression),
+                span: expression.span(),
+                parent,
+                children: 0..0,
+            });
+        }
+        start..self.nodes.len() as u32
+    }
+
+    fn fill(&mut self, id: NodeId, expression: &Expression) {
+        let children = expression.children();
+        let range = self.allocate(children.iter().copied(), Some(id));
+        self.nodes[id.index()].children = range.clone();
+        for (child, expression) in range.zip(children) {
+            self.fill(NodeId(child), expression);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.index())
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self[id].parent
+    }
+
+    pub fn children(
+        &self,
+        id: NodeId,
+    ) -> impl DoubleEndedIterator<Item = NodeId> + ExactSizeIterator + use<> {
+        self[id].children.clone().map(NodeId)
+    }
+
+    /// The parent, grandparent and so on up to the root.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), |&id| self.parent(id))
+    }
+
+    /// `id` and everything below it, parents before children.
+    pub fn descendants(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let mut stack = vec![id];
+        std::iter::from_fn(move || {
+            let id = stack.pop()?;
+            stack.extend(self.children(id).rev());
+            Some(id)
+        })
+    }
+
+    /// Every node id, in allocation order.
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> + use<> {
+        (0..self.nodes.len() as u32).map(NodeId)
+    }
+}
+
+impl Index<NodeId> for Arena {
+    type Output = Node;
+
+    fn index(&self, id: NodeId) -> &Node {
+        &self.nodes[id.index()]
+    }
+}
+
+impl From<&Expression> for Arena {
+    fn from(expression: &Expression) -> Self {
+        Arena::from_expression(expression)
+    }
+}
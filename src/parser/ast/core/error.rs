@@ -0,0 +1,34 @@
+use super::position::Span;
+use super::trivia::Trivia;
+use super::super::errors::RenderError;
+use crate::diagnostic::Diagnostic;
+use serde::{Deserialize, Serialize};
+
+/// A region of source that could not be parsed. The recovering parser puts
+/// these where an expression, binding or list element was expected so the
+/// rest of the tree stays usable. The node spans what its diagnostic points
+/// at.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Error {
+    pub diagnostic: Box<Diagnostic>,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
+}
+
+impl Error {
+    pub fn new(diagnostic: Box<Diagnostic>) -> Self {
+        Self { diagnostic, trivia: Trivia::default() }
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.diagnostic.span
+    }
+
+    pub fn debug(&self) -> String {
+        format!("Error({})", self.diagnostic.message)
+    }
+
+    pub fn render(&self) -> Result<String, RenderError> {
+        Err(RenderError::InvalidNode(self.diagnostic.message.clone()))
+    }
+}
@@ -1,34 +1,61 @@
-use super::position::{Position, Span};
-use super::super::super::parser::Rule;
+use super::position::Span;
+use super::trivia::Trivia;
+use super::super::super::parser::{NixParser, Rule};
+use pest::Parser;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "IdentifierFields")]
 pub struct Identifier {
     pub id: String,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl Identifier {
     pub fn new_span(id: String, span: Span) -> Self {
-        Self { id, span }
+        Self { id, span, trivia: Trivia::default() }
     }
 
     pub fn new(id: String) -> Self {
-        Self {
-            id,
-            span: Span::new(Position::new(1, 1), Position::new(1, 1)),
-        }
+        Self::new_span(id, Span::detached())
     }
 
     pub fn debug(&self) -> String {
-        format!("{}", self.id)
+        self.id.to_string()
     }
 
     pub fn render(&self) -> String {
-        format!("{}", self.id)
+        self.id.to_string()
     }
 
     pub fn parse(pair: pest::iterators::Pair<Rule>) -> Self {
         Self::new_span(pair.as_str().to_string(), Span::from_pest_span(pair.as_span()))
     }
+}
+
+/// The fields of an imported `Identifier`, whose name has to read back as
+/// the same identifier.
+#[derive(Deserialize)]
+struct IdentifierFields {
+    id: String,
+    #[serde(default)]
+    span: Span,
+    #[serde(default)]
+    trivia: Trivia,
+}
 
+impl TryFrom<IdentifierFields> for Identifier {
+    type Error = String;
+
+    fn try_from(fields: IdentifierFields) -> Result<Self, String> {
+        let whole = NixParser::parse(Rule::identifier, &fields.id)
+            .is_ok_and(|mut pairs| pairs.next().is_some_and(|pair| pair.as_str() == fields.id));
+        if !whole {
+            return Err(format!("`{}` is not an identifier", fields.id));
+        }
+        Ok(Self { id: fields.id, span: fields.span, trivia: fields.trivia })
+    }
 }
\ No newline at end of file
@@ -0,0 +1,118 @@
+use std::ops::Range;
+
+use super::position::{Position, Span};
+
+/// A zero-based line and column, as used by editors. Whether `col` counts
+/// UTF-8 bytes or UTF-16 code units depends on the [`LineIndex`] method it
+/// came from or goes to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
+impl LineCol {
+    pub fn new(line: u32, col: u32) -> Self {
+        Self { line, col }
+    }
+}
+
+/// Converts between byte offsets, UTF-8 and UTF-16 line/column pairs, and
+/// [`Position`]s for one source text. Lines end at `\n`; a `\r` before it
+/// counts as part of the line.
+#[derive(Clone, Debug)]
+pub struct LineIndex {
+    text: String,
+    /// Byte offset of the start of every line.
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { text: text.to_string(), line_starts }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Byte range of `line`, without its `\n`.
+    pub fn line_range(&self, line: u32) -> Option<Range<usize>> {
+        let start = *self.line_starts.get(line as usize)?;
+        let end = self
+            .line_starts
+            .get(line as usize + 1)
+            .map_or(self.text.len(), |next| next - 1);
+        Some(start..end)
+    }
+
+    /// Line and UTF-8 byte column of `offset`. Offsets past the end are
+    /// clamped, and offsets inside a character move back to its start.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = self.floor_char_boundary(offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        LineCol::new(line as u32, (offset - self.line_starts[line]) as u32)
+    }
+
+    /// Line and UTF-16 column of `offset`, the LSP default.
+    pub fn line_col_utf16(&self, offset: usize) -> LineCol {
+        let utf8 = self.line_col(offset);
+        let start = self.line_starts[utf8.line as usize];
+        let col = self.text[start..start + utf8.col as usize].encode_utf16().count();
+        LineCol::new(utf8.line, col as u32)
+    }
+
+    /// Byte offset of a line and UTF-8 column, if it is inside the text and
+    /// on a character boundary.
+    pub fn offset(&self, line_col: LineCol) -> Option<usize> {
+        let range = self.line_range(line_col.line)?;
+        let offset = range.start + line_col.col as usize;
+        (offset <= range.end && self.text.is_char_boundary(offset)).then_some(offset)
+    }
+
+    /// Byte offset of a line and UTF-16 column, if it is inside the text and
+    /// not in the middle of a surrogate pair.
+    pub fn offset_utf16(&self, line_col: LineCol) -> Option<usize> {
+        let range = self.line_range(line_col.line)?;
+        let mut units = 0;
+        for (index, c) in self.text[range.clone()].char_indices() {
+            if units == line_col.col as usize {
+                return Some(range.start + index);
+            }
+            units += c.len_utf16();
+        }
+        (units == line_col.col as usize).then_some(range.end)
+    }
+
+    pub fn to_utf16(&self, line_col: LineCol) -> Option<LineCol> {
+        self.offset(line_col).map(|offset| self.line_col_utf16(offset))
+    }
+
+    pub fn to_utf8(&self, line_col: LineCol) -> Option<LineCol> {
+        self.offset_utf16(line_col).map(|offset| self.line_col(offset))
+    }
+
+    /// The [`Position`] of `offset`, with a 1-based line and character column.
+    pub fn position(&self, offset: usize) -> Position {
+        let line_col = self.line_col(offset);
+        let start = self.line_starts[line_col.line as usize];
+        let offset = start + line_col.col as usize;
+        let column = self.text[start..offset].chars().count() + 1;
+        Position::new(line_col.line as i64 + 1, column as i64, offset)
+    }
+
+    pub fn span(&self, range: Range<usize>) -> Span {
+        Span::new(self.position(range.start), self.position(range.end))
+    }
+
+    fn floor_char_boundary(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+}
@@ -1,117 +1,328 @@
-use super::position::{Position, Span};
+use super::position::Span;
+use super::trivia::Trivia;
+use super::super::errors::RenderError;
+use crate::diagnostic::{Diagnostic, codes};
+use super::super::expr::expression::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "LiteralFields<i64>")]
 pub struct Integer {
-    pub value: String,
+    pub value: i64,
+    /// The literal exactly as written, e.g. `007`.
+    pub lexeme: String,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl Integer {
-    pub fn new_span(value: String, span: Span) -> Self {
-        Self { value, span }
+    pub fn new_span(value: i64, lexeme: String, span: Span) -> Self {
+        Self { value, lexeme, span, trivia: Trivia::default() }
     }
 
-    pub fn new(value: String) -> Self {
-        Self::new_span(value, Span::new(Position::new(1, 1), Position::new(1, 1)))
+    pub fn new(value: i64) -> Self {
+        Self::new_span(
+            value,
+            value.to_string(),
+            Span::detached(),
+        )
+    }
+
+    /// Parses an integer lexeme, failing if it does not fit in an `i64`.
+    pub fn from_lexeme(lexeme: &str, span: Span) -> Result<Self, Box<Diagnostic>> {
+        match lexeme.parse() {
+            Ok(value) => Ok(Self::new_span(value, lexeme.to_string(), span)),
+            Err(_) => Err(Diagnostic::error(
+                codes::INTEGER_OUT_OF_RANGE,
+                format!("integer literal `{}` does not fit in a 64-bit signed integer", lexeme),
+                span,
+            )
+            .with_help("integers range from -9223372036854775808 to 9223372036854775807".to_string())
+            .into()),
+        }
     }
 
     pub fn debug(&self) -> String {
-        format!("Integer('{}')", self.value)
+        format!("Integer('{}')", self.lexeme)
     }
 
     pub fn render(&self) -> String {
-        format!("{}", self.value)
+        self.lexeme.clone()
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "LiteralFields<f64>")]
 pub struct Float {
-    pub value: String,
+    pub value: f64,
+    /// The literal exactly as written, e.g. `.5` or `2.5E-3`.
+    pub lexeme: String,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl Float {
-    pub fn new_span(value: String, span: Span) -> Self {
-        Self { value, span }
+    pub fn new_span(value: f64, lexeme: String, span: Span) -> Self {
+        Self { value, lexeme, span, trivia: Trivia::default() }
     }
 
-    pub fn new(value: String) -> Self {
-        Self {
+    /// Builds a float from its value, failing for NaN and the infinities,
+    /// which have no literal in Nix.
+    pub fn new(value: f64) -> Result<Self, Box<Diagnostic>> {
+        if !value.is_finite() {
+            return Err(Diagnostic::error(
+                codes::FLOAT_OUT_OF_RANGE,
+                format!("{} has no float literal", value),
+                Span::detached(),
+            ).into());
+        }
+        // `Debug` always keeps a `.` or an exponent, so the lexeme never
+        // reads back as an integer.
+        Ok(Self::new_span(
             value,
-            span: Span::new(Position::new(1, 1), Position::new(1, 1)),
+            format!("{:?}", value),
+            Span::detached(),
+        ))
+    }
+
+    /// Parses a float lexeme, failing if it is out of the range of an `f64`.
+    pub fn from_lexeme(lexeme: &str, span: Span) -> Result<Self, Box<Diagnostic>> {
+        match lexeme.parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(Self::new_span(value, lexeme.to_string(), span)),
+            _ => Err(Diagnostic::error(
+                codes::FLOAT_OUT_OF_RANGE,
+                format!("float literal `{}` is out of range", lexeme),
+                span,
+            ).into()),
         }
     }
 
     pub fn debug(&self) -> String {
-        format!("Float('{}')", self.value)
+        format!("Float('{}')", self.lexeme)
     }
 
     pub fn render(&self) -> String {
-        format!("{}", self.value)
+        self.lexeme.clone()
     }
 }
 
-#[derive(Clone, Debug)]
+/// The fields of an imported `Integer` or `Float`, whose lexeme may be left
+/// out and is otherwise checked against the value.
+#[derive(Deserialize)]
+struct LiteralFields<T> {
+    value: T,
+    lexeme: Option<String>,
+    #[serde(default)]
+    span: Span,
+    #[serde(default)]
+    trivia: Trivia,
+}
+
+impl TryFrom<LiteralFields<i64>> for Integer {
+    type Error = String;
+
+    fn try_from(fields: LiteralFields<i64>) -> Result<Self, String> {
+        let lexeme = fields.lexeme.unwrap_or_else(|| fields.value.to_string());
+        let digits = lexeme.strip_prefix('-').unwrap_or(&lexeme);
+        let valid = !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+        if !valid || lexeme.parse() != Ok(fields.value) {
+            return Err(format!("lexeme `{}` does not spell integer {}", lexeme, fields.value));
+        }
+        let mut integer = Self::new_span(fields.value, lexeme, fields.span);
+        integer.trivia = fields.trivia;
+        Ok(integer)
+    }
+}
+
+impl TryFrom<LiteralFields<f64>> for Float {
+    type Error = String;
+
+    fn try_from(fields: LiteralFields<f64>) -> Result<Self, String> {
+        let mut float = match fields.lexeme {
+            Some(lexeme) => Self::new_span(fields.value, lexeme, fields.span),
+            None => Self { span: fields.span, ..Self::new(fields.value).map_err(|e| e.message)? },
+        };
+        // A lexeme without `.` or an exponent would read back as an integer.
+        let digits = float.lexeme.strip_prefix('-').unwrap_or(&float.lexeme);
+        let valid = digits.contains(['.', 'e', 'E'])
+            && digits.bytes().all(|b| b.is_ascii_digit() || b".eE+-".contains(&b));
+        if !valid || float.lexeme.parse() != Ok(fields.value) {
+            return Err(format!("lexeme `{}` does not spell float {}", float.lexeme, fields.value));
+        }
+        float.trivia = fields.trivia;
+        Ok(float)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LiteralString {
     pub value: String,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl LiteralString {
     pub fn new_span(value: String, span: Span) -> Self {
-        Self { value, span }
+        Self { value, span, trivia: Trivia::default() }
     }
 
     pub fn new(value: String) -> Self {
-        Self::new_span(value, Span::new(Position::new(1, 1), Position::new(1, 1)))
+        Self::new_span(value, Span::detached())
     }
 
     pub fn debug(&self) -> String {
         format!("LiteralString('{}')", self.value)
     }
 
+    /// Renders the value as a double-quoted string, escaping whatever Nix
+    /// would otherwise read as the end of the string or an interpolation.
     pub fn render(&self) -> String {
-        format!("{}", self.value)
+        let mut rendered = String::with_capacity(self.value.len() + 2);
+        rendered.push('"');
+        let mut chars = self.value.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => rendered.push_str("\\\""),
+                '\\' => rendered.push_str("\\\\"),
+                '\n' => rendered.push_str("\\n"),
+                '\r' => rendered.push_str("\\r"),
+                '\t' => rendered.push_str("\\t"),
+                '$' if chars.peek() == Some(&'{') => rendered.push_str("\\$"),
+                c => rendered.push(c),
+            }
+        }
+        rendered.push('"');
+        rendered
     }
 }
 
-#[derive(Clone, Debug)]
+/// Where a `Path` is rooted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathKind {
+    /// `./a/b`, `../a` or `a/b`, resolved against the containing file.
+    Relative,
+    /// `/etc/x`.
+    Absolute,
+    /// `~/x`, resolved against the user's home directory.
+    Home,
+}
+
+/// A filesystem path literal. `parts` holds `PartRaw` text and
+/// `PartInterpolation`s, as in `./${name}.nix`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Path {
-    pub parts: Vec<String>,
+    #[serde(rename = "path_kind")]
+    pub kind: PathKind,
+    pub parts: Vec<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl Path {
-    pub fn new_span(parts: Vec<String>, span: Span) -> Self {
-        Self { parts, span }
+    pub fn new_span(kind: PathKind, parts: Vec<Expression>, span: Span) -> Self {
+        Self { kind, parts, span, trivia: Trivia::default() }
+    }
+
+    pub fn new(kind: PathKind, parts: Vec<Expression>) -> Self {
+        Self::new_span(kind, parts, Span::detached())
     }
 
-    pub fn new(parts: Vec<String>) -> Self {
-        Self::new_span(parts, Span::new(Position::new(1, 1), Position::new(1, 1)))
+    pub fn render(&self) -> Result<String, RenderError> {
+        Ok(self
+            .parts
+            .iter()
+            .map(|p| p.render())
+            .collect::<Result<Vec<_>, _>>()?
+            .join(""))
+    }
+
+    pub fn debug(&self) -> String {
+        format!("Path({:?}, {:?})", self.kind, self.parts)
+    }
+}
+
+/// A search path looked up in `NIX_PATH`, such as `<nixpkgs>` or
+/// `<nixpkgs/lib>`. `path` is the text between the angle brackets.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchNixPath {
+    pub path: String,
+    #[serde(default)]
+    pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
+}
+
+impl SearchNixPath {
+    pub fn new_span(path: String, span: Span) -> Self {
+        Self { path, span, trivia: Trivia::default() }
+    }
+
+    pub fn new(path: String) -> Self {
+        Self::new_span(path, Span::detached())
+    }
+
+    pub fn render(&self) -> String {
+        format!("<{}>", self.path)
+    }
+
+    pub fn debug(&self) -> String {
+        format!("SearchNixPath('{}')", self.path)
+    }
+}
+
+/// An unquoted URI such as `https://example.com`, which Nix reads as a string.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Uri {
+    pub uri: String,
+    #[serde(default)]
+    pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
+}
+
+impl Uri {
+    pub fn new_span(uri: String, span: Span) -> Self {
+        Self { uri, span, trivia: Trivia::default() }
+    }
+
+    pub fn new(uri: String) -> Self {
+        Self::new_span(uri, Span::detached())
     }
 
     pub fn render(&self) -> String {
-        self.parts.join("/")
+        self.uri.clone()
     }
 
     pub fn debug(&self) -> String {
-        format!("Path({:?})", self.parts)
+        format!("Uri('{}')", self.uri)
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Boolean {
     pub value: bool,
-    pub span: Span
+    #[serde(default)]
+    pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl Boolean {
     pub fn new_span(value: bool, span: Span) -> Self {
-        Self { value, span }
+        Self { value, span, trivia: Trivia::default() }
     }
 
     pub fn new(value: bool) -> Self {
-        Self::new_span(value, Span::new(Position::new(1, 1), Position::new(1, 1)))
+        Self::new_span(value, Span::detached())
     }
 
     pub fn debug(&self) -> String {
@@ -119,22 +330,25 @@ impl Boolean {
     }
 
     pub fn render(&self) -> String {
-        format!("{}", self.value)
+        self.value.to_string()
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Null {
-    pub span: Span
+    #[serde(default)]
+    pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl Null {
     pub fn new_span(span: Span) -> Self {
-        Self { span }
+        Self { span, trivia: Trivia::default() }
     }
 
     pub fn new() -> Self {
-        Self::new_span(Span::new(Position::new(1, 1), Position::new(1, 1)))
+        Self::new_span(Span::detached())
     }
 
     pub fn debug(&self) -> String {
@@ -146,3 +360,9 @@ impl Null {
     }
 }
 
+impl Default for Null {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
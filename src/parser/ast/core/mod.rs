@@ -1,9 +1,15 @@
 pub mod position;
+pub mod line_index;
 pub mod literal;
 pub mod identifier;
 pub mod operators;
+pub mod trivia;
+pub mod error;
 
 pub use position::{Position, Span};
-pub use literal::{Integer, Float, LiteralString, Path, Boolean, Null};
+pub use line_index::{LineCol, LineIndex};
+pub use literal::{Integer, Float, LiteralString, Path, PathKind, SearchNixPath, Uri, Boolean, Null};
 pub use identifier::Identifier;
-pub use operators::{Operator, Addition, Subtraction, Multiplication, Division, EqualTo, NotEqualTo, GreaterThan, GreaterThanOrEqualTo, LessThan, LessThanOrEqualTo};
\ No newline at end of file
+pub use trivia::{Comment, Trivia};
+pub use error::Error;
+pub use operators::{Operator, Associativity, Addition, Subtraction, Multiplication, Division, EqualTo, NotEqualTo, GreaterThan, GreaterThanOrEqualTo, LessThan, LessThanOrEqualTo};
\ No newline at end of file
@@ -1,7 +1,10 @@
+use serde::de::{self, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
+
 macro_rules! impl_operator {
     ($( $name:ident => $render:expr ),+ $(,)?) => {
         $(
-            #[derive(Clone, Copy, Debug)]
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
             pub struct $name;
 
             impl $name {
@@ -37,11 +40,34 @@ impl_operator!(
     Not => "!",
     Negate => "-",
     Concatenation => "++",
-    Implication => "=>",
-    Update => ":=",
+    Implication => "->",
+    Update => "//",
 );
 
-#[derive(Clone, Debug)]
+/// Precedences of the forms that are not operators, on the same scale as
+/// [`Operator::precedence`].
+pub mod precedence {
+    /// Literals, identifiers, strings, lists, attribute sets and anything in
+    /// parentheses.
+    pub const ATOM: u8 = 0;
+    pub const SELECT: u8 = 1;
+    pub const APPLICATION: u8 = 2;
+    pub const HAS_ATTRIBUTE: u8 = 4;
+    /// Functions, `let`, `with`, `if` and `assert`, whose bodies extend as far
+    /// to the right as possible.
+    pub const LOWEST: u8 = 15;
+}
+
+/// How a chain of operators with the same precedence is grouped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+    /// Chaining is a syntax error, e.g. `a < b < c`.
+    None,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Operator {
     Addition(Addition),
     Concatenation(Concatenation),
@@ -85,6 +111,53 @@ impl Operator {
         }
     }
 
+    /// The variant name, e.g. `"Addition"`. This is how operators appear in
+    /// the JSON export.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operator::Addition(_) => Addition::value(),
+            Operator::Concatenation(_) => Concatenation::value(),
+            Operator::EqualTo(_) => EqualTo::value(),
+            Operator::GreaterThan(_) => GreaterThan::value(),
+            Operator::GreaterThanOrEqualTo(_) => GreaterThanOrEqualTo::value(),
+            Operator::Division(_) => Division::value(),
+            Operator::Implication(_) => Implication::value(),
+            Operator::LessThan(_) => LessThan::value(),
+            Operator::LessThanOrEqualTo(_) => LessThanOrEqualTo::value(),
+            Operator::LogicalAnd(_) => LogicalAnd::value(),
+            Operator::LogicalOr(_) => LogicalOr::value(),
+            Operator::Multiplication(_) => Multiplication::value(),
+            Operator::NotEqualTo(_) => NotEqualTo::value(),
+            Operator::Subtraction(_) => Subtraction::value(),
+            Operator::Update(_) => Update::value(),
+            Operator::Not(_) => Not::value(),
+            Operator::Negate(_) => Negate::value(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Addition" => Some(Operator::Addition(Addition)),
+            "Concatenation" => Some(Operator::Concatenation(Concatenation)),
+            "EqualTo" => Some(Operator::EqualTo(EqualTo)),
+            "GreaterThan" => Some(Operator::GreaterThan(GreaterThan)),
+            "GreaterThanOrEqualTo" => Some(Operator::GreaterThanOrEqualTo(GreaterThanOrEqualTo)),
+            "Division" => Some(Operator::Division(Division)),
+            "Implication" => Some(Operator::Implication(Implication)),
+            "LessThan" => Some(Operator::LessThan(LessThan)),
+            "LessThanOrEqualTo" => Some(Operator::LessThanOrEqualTo(LessThanOrEqualTo)),
+            "LogicalAnd" => Some(Operator::LogicalAnd(LogicalAnd)),
+            "LogicalOr" => Some(Operator::LogicalOr(LogicalOr)),
+            "Multiplication" => Some(Operator::Multiplication(Multiplication)),
+            "NotEqualTo" => Some(Operator::NotEqualTo(NotEqualTo)),
+            "Subtraction" => Some(Operator::Subtraction(Subtraction)),
+            "Update" => Some(Operator::Update(Update)),
+            "Not" => Some(Operator::Not(Not)),
+            "Negate" => Some(Operator::Negate(Negate)),
+            _ => None,
+        }
+    }
+
     pub fn is_unary(&self) -> bool {
         matches!(self, Operator::Not(_) | Operator::Negate(_))
     }
@@ -93,4 +166,54 @@ impl Operator {
         !self.is_unary()
     }
 
-}
\ No newline at end of file
+    /// Precedence as listed in the Nix manual, where lower numbers bind
+    /// tighter. Attribute selection is 1 and function application is 2.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Operator::Negate(_) => 3,
+            Operator::Concatenation(_) => 5,
+            Operator::Multiplication(_) | Operator::Division(_) => 6,
+            Operator::Addition(_) | Operator::Subtraction(_) => 7,
+            Operator::Not(_) => 8,
+            Operator::Update(_) => 9,
+            Operator::LessThan(_)
+            | Operator::LessThanOrEqualTo(_)
+            | Operator::GreaterThan(_)
+            | Operator::GreaterThanOrEqualTo(_) => 10,
+            Operator::EqualTo(_) | Operator::NotEqualTo(_) => 11,
+            Operator::LogicalAnd(_) => 12,
+            Operator::LogicalOr(_) => 13,
+            Operator::Implication(_) => 14,
+        }
+    }
+
+    pub fn associativity(&self) -> Associativity {
+        match self {
+            Operator::Concatenation(_) | Operator::Update(_) | Operator::Implication(_) => {
+                Associativity::Right
+            }
+            Operator::LessThan(_)
+            | Operator::LessThanOrEqualTo(_)
+            | Operator::GreaterThan(_)
+            | Operator::GreaterThanOrEqualTo(_)
+            | Operator::EqualTo(_)
+            | Operator::NotEqualTo(_) => Associativity::None,
+            _ => Associativity::Left,
+        }
+    }
+
+}
+
+impl Serialize for Operator {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Operator {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Operator::from_name(&name)
+            .ok_or_else(|| de::Error::custom(format!("unknown operator `{name}`")))
+    }
+}
@@ -1,18 +1,31 @@
+use std::ops::Range;
+
 use pest::Span as PestSpan;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+/// A point in the source. `line` and `column` are 1-based and `column` counts
+/// characters, as shown in messages; `offset` is the byte offset from the
+/// start of the source. Use a [`LineIndex`](super::LineIndex) to convert to
+/// other encodings.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Position {
     pub line: i64,
     pub column: i64,
+    pub offset: usize,
 }
 
 impl Position {
-    pub fn new(line: i64, column: i64) -> Self {
-        Self { line, column }
+    pub fn new(line: i64, column: i64, offset: usize) -> Self {
+        Self { line, column, offset }
+    }
+
+    pub fn from_pest_position(position: pest::Position) -> Self {
+        let (line, column) = position.line_col();
+        Position::new(line as i64, column as i64, position.pos())
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Span {
     pub start: Position,
     pub end: Position,
@@ -23,12 +36,37 @@ impl Span {
         Self { start, end }
     }
 
+    /// The span of a node built in code rather than parsed. Its line and
+    /// column are 0, which no parsed position has.
+    pub fn detached() -> Self {
+        Span::new(Position::new(0, 0, 0), Position::new(0, 0, 0))
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self.start.line == 0
+    }
+
     pub fn from_pest_span(span: PestSpan) -> Self {
-        let (start_line, start_col) = span.start_pos().line_col();
-        let (end_line, end_col) = span.end_pos().line_col();
         Span::new(
-            Position::new(start_line as i64, start_col as i64),
-            Position::new(end_line as i64, end_col as i64),
+            Position::from_pest_position(span.start_pos()),
+            Position::from_pest_position(span.end_pos()),
         )
     }
+
+    /// The byte range covered by the span.
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+
+    /// Whether `offset` lies within the span, its end included so that a
+    /// cursor right after a node still counts as on it.
+    pub fn contains_offset(&self, offset: usize) -> bool {
+        self.start.offset <= offset && offset <= self.end.offset
+    }
+}
+
+impl Default for Span {
+    fn default() -> Self {
+        Span::detached()
+    }
 }
@@ -0,0 +1,64 @@
+use super::position::Span;
+use serde::{Deserialize, Serialize};
+
+/// A `# line` or `/* block */` comment, including its delimiters.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Comment {
+    pub text: String,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl Comment {
+    pub fn new(text: String, span: Span) -> Self {
+        Self { text, span }
+    }
+
+    /// Line comments run to the end of the line, so whatever follows them
+    /// has to start on a new one.
+    pub fn is_line(&self) -> bool {
+        self.text.starts_with('#')
+    }
+
+    pub fn debug(&self) -> String {
+        format!("Comment({:?})", self.text)
+    }
+}
+
+/// Comments attached to a node: `leading` ones come before it, `trailing`
+/// ones follow it on the same line, and `inner` ones sit between the brackets
+/// of an empty list or attribute set.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Trivia {
+    pub leading: Vec<Comment>,
+    pub trailing: Vec<Comment>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inner: Vec<Comment>,
+}
+
+impl Trivia {
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_empty() && self.inner.is_empty()
+    }
+
+    /// Wraps an already rendered node with its leading and trailing comments.
+    pub fn render(&self, node: String) -> String {
+        if self.is_empty() {
+            return node;
+        }
+        let mut result = String::new();
+        for comment in &self.leading {
+            result.push_str(&comment.text);
+            result.push(if comment.is_line() { '\n' } else { ' ' });
+        }
+        result.push_str(&node);
+        for comment in &self.trailing {
+            result.push(' ');
+            result.push_str(&comment.text);
+            if comment.is_line() {
+                result.push('\n');
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,217 @@
+//! Equality and hashing of AST nodes.
+//!
+//! `PartialEq`, `Eq` and `Hash` on nodes are structural: they compare what a
+//! node means and ignore where it is, so `a + 1` parsed from two different
+//! files compares equal, and a parsed tree compares equal to the same tree
+//! built in code. Spans and comments are ignored. Literals compare by lexeme,
+//! so `7` and `007` differ.
+//!
+//! [`SpannedEq`] additionally compares spans and comments, for checking that
+//! a transformation left positions untouched.
+
+use std::hash::{Hash, Hasher};
+
+use crate::diagnostic::Diagnostic;
+
+use super::core::operators::Operator;
+use super::core::{
+    Boolean, Comment, Error, Float, Identifier, Integer, LiteralString, Null, Path, PathKind,
+    SearchNixPath, Span, Trivia, Uri,
+};
+use super::expr::{
+    Assert, AttrPath, AttrSet, BinaryOperation, BindingInherit, BindingKeyValue, Expression,
+    Function, FunctionApplication, FunctionHead, FunctionHeadDestructured,
+    FunctionHeadDestructuredArgument, FunctionHeadSimple, HasAttribute, IfThenElse,
+    IndentedString, LetIn, List, NixString, PartInterpolation, PartRaw, PropertyAccess, Throw,
+    UnaryOperation, With,
+};
+
+/// Equality that also compares spans and comments.
+pub trait SpannedEq {
+    fn spanned_eq(&self, other: &Self) -> bool;
+}
+
+impl<T: SpannedEq + ?Sized> SpannedEq for Box<T> {
+    fn spanned_eq(&self, other: &Self) -> bool {
+        (**self).spanned_eq(&**other)
+    }
+}
+
+impl<T: SpannedEq> SpannedEq for Option<T> {
+    fn spanned_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Some(a), Some(b)) => a.spanned_eq(b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T: SpannedEq> SpannedEq for Vec<T> {
+    fn spanned_eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a.spanned_eq(b))
+    }
+}
+
+/// Types without positions of their own, where both comparisons agree.
+macro_rules! spanned_eq_by_eq {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl SpannedEq for $ty {
+                fn spanned_eq(&self, other: &Self) -> bool {
+                    self == other
+                }
+            }
+        )+
+    };
+}
+
+spanned_eq_by_eq!(String, bool, Span, Operator, PathKind, Diagnostic);
+
+/// Implements structural `PartialEq`, `Eq` and `Hash` over the fields before
+/// the `;`, and `SpannedEq` over all fields.
+macro_rules! node_eq {
+    ($name:ident { $($field:ident),* ; $($position:ident),* }) => {
+        impl PartialEq for $name {
+            #[allow(unused_variables)]
+            fn eq(&self, other: &Self) -> bool {
+                true $(&& self.$field == other.$field)*
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl Hash for $name {
+            #[allow(unused_variables)]
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                $(self.$field.hash(state);)*
+            }
+        }
+
+        impl SpannedEq for $name {
+            fn spanned_eq(&self, other: &Self) -> bool {
+                true $(&& self.$field.spanned_eq(&other.$field))*
+                    $(&& self.$position.spanned_eq(&other.$position))*
+            }
+        }
+    };
+}
+
+node_eq!(Comment { text; span });
+node_eq!(Trivia { leading, trailing, inner; });
+
+node_eq!(Identifier { id; span, trivia });
+node_eq!(Integer { lexeme; span, trivia });
+node_eq!(Float { lexeme; span, trivia });
+node_eq!(LiteralString { value; span, trivia });
+node_eq!(Boolean { value; span, trivia });
+node_eq!(Null { ; span, trivia });
+node_eq!(Path { kind, parts; span, trivia });
+node_eq!(SearchNixPath { path; span, trivia });
+node_eq!(Uri { uri; span, trivia });
+
+node_eq!(UnaryOperation { operator, expression; span, trivia });
+node_eq!(BinaryOperation { left, operator, right; span, trivia });
+node_eq!(List { elements; span, trivia });
+node_eq!(AttrSet { recursive, bindings; span, trivia });
+node_eq!(IfThenElse { predicate, then, else_; span, trivia });
+node_eq!(FunctionHeadDestructuredArgument { identifier, default; span, trivia });
+node_eq!(FunctionHeadDestructured {
+    ellipsis, identifier, identifier_before, arguments; span
+});
+node_eq!(FunctionHeadSimple { identifier; span });
+node_eq!(Function { head, body; span, trivia });
+node_eq!(FunctionApplication { function, arguments; span, trivia });
+node_eq!(AttrPath { attributes; span });
+node_eq!(PropertyAccess { expression, attribute_path, default; span, trivia });
+node_eq!(HasAttribute { expression, attribute_path; span, trivia });
+node_eq!(NixString { parts; span, trivia });
+node_eq!(IndentedString { parts; span, trivia });
+node_eq!(PartRaw { content; span, trivia });
+node_eq!(PartInterpolation { expression; span, trivia });
+node_eq!(BindingInherit { from_, attributes; span, trivia });
+node_eq!(BindingKeyValue { from_, to; span, trivia });
+node_eq!(With { scope, body; span, trivia });
+node_eq!(LetIn { bindings, target; span, trivia });
+node_eq!(Assert { condition, body; span, trivia });
+node_eq!(Throw { message; span, trivia });
+
+// Two error nodes are the same problem if they say the same thing, wherever
+// it was found.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        self.diagnostic.code == other.diagnostic.code
+            && self.diagnostic.message == other.diagnostic.message
+    }
+}
+
+impl Eq for Error {}
+
+impl Hash for Error {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.diagnostic.code.hash(state);
+        self.diagnostic.message.hash(state);
+    }
+}
+
+impl SpannedEq for Error {
+    fn spanned_eq(&self, other: &Self) -> bool {
+        self.diagnostic == other.diagnostic && self.trivia.spanned_eq(&other.trivia)
+    }
+}
+
+impl SpannedEq for FunctionHead {
+    fn spanned_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FunctionHead::FunctionHeadSimple(a), FunctionHead::FunctionHeadSimple(b)) => {
+                a.spanned_eq(b)
+            }
+            (
+                FunctionHead::FunctionHeadDestructured(a),
+                FunctionHead::FunctionHeadDestructured(b),
+            ) => a.spanned_eq(b),
+            _ => false,
+        }
+    }
+}
+
+impl SpannedEq for Expression {
+    fn spanned_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expression::Integer(a), Expression::Integer(b)) => a.spanned_eq(b),
+            (Expression::Float(a), Expression::Float(b)) => a.spanned_eq(b),
+            (Expression::Identifier(a), Expression::Identifier(b)) => a.spanned_eq(b),
+            (Expression::LiteralString(a), Expression::LiteralString(b)) => a.spanned_eq(b),
+            (Expression::Boolean(a), Expression::Boolean(b)) => a.spanned_eq(b),
+            (Expression::Null(a), Expression::Null(b)) => a.spanned_eq(b),
+            (Expression::Path(a), Expression::Path(b)) => a.spanned_eq(b),
+            (Expression::SearchNixPath(a), Expression::SearchNixPath(b)) => a.spanned_eq(b),
+            (Expression::Uri(a), Expression::Uri(b)) => a.spanned_eq(b),
+            (Expression::UnaryOperation(a), Expression::UnaryOperation(b)) => a.spanned_eq(b),
+            (Expression::BinaryOperation(a), Expression::BinaryOperation(b)) => a.spanned_eq(b),
+            (Expression::List(a), Expression::List(b)) => a.spanned_eq(b),
+            (Expression::AttrSet(a), Expression::AttrSet(b)) => a.spanned_eq(b),
+            (Expression::IfThenElse(a), Expression::IfThenElse(b)) => a.spanned_eq(b),
+            (Expression::Function(a), Expression::Function(b)) => a.spanned_eq(b),
+            (Expression::FunctionApplication(a), Expression::FunctionApplication(b)) => {
+                a.spanned_eq(b)
+            }
+            (Expression::PropertyAccess(a), Expression::PropertyAccess(b)) => a.spanned_eq(b),
+            (Expression::HasAttribute(a), Expression::HasAttribute(b)) => a.spanned_eq(b),
+            (Expression::NixString(a), Expression::NixString(b)) => a.spanned_eq(b),
+            (Expression::IndentedString(a), Expression::IndentedString(b)) => a.spanned_eq(b),
+            (Expression::PartRaw(a), Expression::PartRaw(b)) => a.spanned_eq(b),
+            (Expression::PartInterpolation(a), Expression::PartInterpolation(b)) => {
+                a.spanned_eq(b)
+            }
+            (Expression::BindingInherit(a), Expression::BindingInherit(b)) => a.spanned_eq(b),
+            (Expression::BindingKeyValue(a), Expression::BindingKeyValue(b)) => a.spanned_eq(b),
+            (Expression::With(a), Expression::With(b)) => a.spanned_eq(b),
+            (Expression::LetIn(a), Expression::LetIn(b)) => a.spanned_eq(b),
+            (Expression::Assert(a), Expression::Assert(b)) => a.spanned_eq(b),
+            (Expression::Throw(a), Expression::Throw(b)) => a.spanned_eq(b),
+            (Expression::Error(a), Expression::Error(b)) => a.spanned_eq(b),
+            _ => false,
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use std::fmt;
 
+use super::core::Span;
+use crate::diagnostic::{Diagnostic, codes};
+
 #[derive(Debug)]
 pub enum RenderError {
     InvalidNode(String),
@@ -19,15 +22,10 @@ impl fmt::Display for RenderError {
 
 impl std::error::Error for RenderError {}
 
-#[derive(Debug)]
-pub struct SyntaxError {
-    pub message: String,
-}
-
-impl fmt::Display for SyntaxError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Syntax error: {}", self.message)
+impl RenderError {
+    /// Reports a node that could not be rendered, e.g. when a refactor
+    /// produced a tree that cannot be written back out.
+    pub fn to_diagnostic(&self, span: Span) -> Diagnostic {
+        Diagnostic::error(codes::RENDER_FAILED, self.to_string(), span)
     }
 }
-
-impl std::error::Error for SyntaxError {}
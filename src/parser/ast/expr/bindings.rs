@@ -1,12 +1,19 @@
-use super::super::core::position::{Position, Span};
+use super::super::core::position::Span;
+use super::super::core::trivia::Trivia;
 use super::super::errors::RenderError;
 use super::super::expr::expression::Expression;
+use super::collection::render_block;
+use super::property::AttrPath;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LetIn {
     pub bindings: Vec<Expression>,
     pub target: Box<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl LetIn {
@@ -15,6 +22,7 @@ impl LetIn {
             bindings,
             target: Box::new(target),
             span,
+            trivia: Trivia::default(),
         }
     }
 
@@ -22,54 +30,54 @@ impl LetIn {
         Self::new_span(
             bindings,
             target,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            Span::detached(),
         )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
-        let bindings_str = self
-            .bindings
-            .iter()
-            .map(|b| b.render())
-            .collect::<Result<Vec<_>, _>>()?
-            .join(" ");
-        Ok(format!("let {} in {}", bindings_str, self.target.render()?))
+        let bindings = render_block("let", &self.bindings, &[], "in", Expression::render)?;
+        Ok(format!("{} {}", bindings, self.target.render()?))
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BindingInherit {
+    #[serde(rename = "from")]
     pub from_: Option<Box<Expression>>,
-    pub attributes: Box<Expression>,
+    pub attributes: Vec<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl BindingInherit {
-    pub fn new_span(from_: Option<Expression>, attributes: Expression, span: Span) -> Self {
+    pub fn new_span(from_: Option<Expression>, attributes: Vec<Expression>, span: Span) -> Self {
         Self {
             from_: from_.map(Box::new),
-            attributes: Box::new(attributes),
+            attributes,
             span,
+            trivia: Trivia::default(),
         }
     }
 
-    pub fn new(from_: Option<Expression>, attributes: Expression) -> Self {
+    pub fn new(from_: Option<Expression>, attributes: Vec<Expression>) -> Self {
         Self::new_span(
             from_,
             attributes,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            Span::detached(),
         )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
-        match &self.from_ {
-            Some(from_expr) => Ok(format!(
-                "inherit ( {}) {}",
-                from_expr.render()?,
-                self.attributes.render()?
-            )),
-            None => Ok(format!("inherit {}", self.attributes.render()?)),
+        let mut parts = vec!["inherit".to_string()];
+        if let Some(from_expr) = &self.from_ {
+            parts.push(format!("({})", from_expr.render()?));
+        }
+        for attribute in &self.attributes {
+            parts.push(attribute.render()?);
         }
+        Ok(format!("{};", parts.join(" ")))
     }
 
     pub fn debug(&self) -> String {
@@ -78,22 +86,33 @@ impl BindingInherit {
 }
 
 // MARK: BindingKeyValue
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BindingKeyValue {
-    pub from_: Box<Expression>,
+    #[serde(rename = "from")]
+    pub from_: AttrPath,
     pub to: Box<Expression>,
+    #[serde(default)]
+    pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl BindingKeyValue {
-    pub fn new_span(from_: Expression, to: Expression) -> Self {
+    pub fn new_span(from_: AttrPath, to: Expression, span: Span) -> Self {
         Self {
-            from_: Box::new(from_),
+            from_,
             to: Box::new(to),
+            span,
+            trivia: Trivia::default(),
         }
     }
 
-    pub fn new(from_: Expression, to: Expression) -> Self {
-        Self::new_span(from_, to)
+    pub fn new(from_: AttrPath, to: Expression) -> Self {
+        Self::new_span(
+            from_,
+            to,
+            Span::detached(),
+        )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
@@ -101,15 +120,18 @@ impl BindingKeyValue {
     }
 
     pub fn debug(&self) -> String {
-        format!("KeyValue({:?})", self.from_)
+        format!("KeyValue({})", self.from_.debug())
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct With {
     pub scope: Box<Expression>,
     pub body: Box<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl With {
@@ -118,6 +140,7 @@ impl With {
             scope: Box::new(scope),
             body: Box::new(body),
             span,
+            trivia: Trivia::default(),
         }
     }
 
@@ -125,7 +148,7 @@ impl With {
         Self::new_span(
             scope,
             body,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            Span::detached(),
         )
     }
 
@@ -1,33 +1,35 @@
-use super::super::core::position::{Position, Span};
+use super::super::core::operators::precedence;
+use super::super::core::position::Span;
+use super::super::core::trivia::{Comment, Trivia};
 use super::super::errors::{RenderError};
 use super::expression::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct List {
     pub elements: Vec<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl List {
     pub fn new_span(elements: Vec<Expression>, span: Span) -> Self {
-        Self { elements, span }
+        Self { elements, span, trivia: Trivia::default() }
     }
 
     pub fn new(elements: Vec<Expression>) -> Self {
         Self::new_span(
             elements,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            Span::detached(),
         )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
-        let elems = self
-            .elements
-            .iter()
-            .map(|e| e.render())
-            .collect::<Result<Vec<_>, _>>()?
-            .join(", ");
-        Ok(format!("[{}]", elems))
+        render_block("[", &self.elements, &self.trivia.inner, "]", |e| {
+            e.render_operand(precedence::SELECT)
+        })
     }
 
     pub fn debug(&self) -> String {
@@ -35,11 +37,14 @@ impl List {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct  AttrSet {
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttrSet {
     pub recursive: bool,
     pub bindings: Vec<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl AttrSet {
@@ -48,6 +53,7 @@ impl AttrSet {
             recursive,
             bindings,
             span,
+            trivia: Trivia::default(),
         }
     }
 
@@ -55,25 +61,69 @@ impl AttrSet {
         Self::new_span(
             recursive,
             bindings,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            Span::detached(),
         )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
-        let bindings = self
-            .bindings
-            .iter()
-            .map(|b| b.render())
-            .collect::<Result<Vec<_>, _>>()?
-            .join(", ");
+        let body = render_block("{", &self.bindings, &self.trivia.inner, "}", Expression::render)?;
         Ok(if self.recursive {
-            format!("rec {{ {} }}", bindings)
+            format!("rec {}", body)
         } else {
-            format!("{{ {} }}", bindings)
+            body
         })
     }
 
     pub fn debug(&self) -> String {
         format!(" AttrSet(recursive={}, {:?})", self.recursive, self.bindings)
     }
-}
\ No newline at end of file
+}
+
+/// Renders bracketed items on one line, or one per line when a line comment
+/// among them would otherwise run into the next item. `inner` are the
+/// comments between the brackets when there are no items.
+pub(super) fn render_block(
+    open: &str,
+    items: &[Expression],
+    inner: &[Comment],
+    close: &str,
+    render: impl Fn(&Expression) -> Result<String, RenderError>,
+) -> Result<String, RenderError> {
+    let mut lines: Vec<String> = inner.iter().map(|comment| comment.text.clone()).collect();
+    for item in items {
+        lines.push(render(item)?);
+    }
+    if lines.is_empty() {
+        return Ok(format!("{} {}", open, close));
+    }
+    let vertical =
+        inner.iter().any(Comment::is_line) || lines.iter().any(|line| line.contains('\n'));
+    if !vertical {
+        return Ok(format!("{} {} {}", open, lines.join(" "), close));
+    }
+    let mut rendered = open.to_string();
+    for (index, line) in lines.iter().enumerate() {
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        // Indenting the lines of a string would change its value.
+        let item = index.checked_sub(inner.len()).map(|index| &items[index]);
+        let indent = !item.is_some_and(has_multiline_string);
+        for (number, text) in line.split('\n').enumerate() {
+            rendered.push('\n');
+            if (number == 0 || indent) && !text.is_empty() {
+                rendered.push_str("  ");
+            }
+            rendered.push_str(text);
+        }
+    }
+    rendered.push('\n');
+    rendered.push_str(close);
+    Ok(rendered)
+}
+
+/// Whether a string or path below `expression` has text running over lines.
+fn has_multiline_string(expression: &Expression) -> bool {
+    match expression {
+        Expression::PartRaw(x) => x.content.contains('\n'),
+        _ => expression.children().into_iter().any(has_multiline_string),
+    }
+}
@@ -1,13 +1,19 @@
-use super::super::core::position::{Position, Span};
+use super::super::core::position::Span;
+use super::super::core::trivia::Trivia;
 use super::super::errors::RenderError;
 use super::expression::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct IfThenElse {
     pub predicate: Box<Expression>,
     pub then: Box<Expression>,
+    #[serde(rename = "else")]
     pub else_: Box<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl IfThenElse {
@@ -22,6 +28,7 @@ impl IfThenElse {
             then: Box::new(then),
             else_: Box::new(else_),
             span,
+            trivia: Trivia::default(),
         }
     }
 
@@ -30,7 +37,7 @@ impl IfThenElse {
             predicate,
             then,
             else_,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            Span::detached(),
         )
     }
 
@@ -1,16 +1,24 @@
-use super::super::core::{Boolean, Float, Identifier, Integer, LiteralString, Null, Path};
-use super::operations::BinaryOperation;
+use super::super::core::{
+    Boolean, Error, Float, Identifier, Integer, LiteralString, Null, Path, SearchNixPath, Uri,
+};
+use super::operations::{BinaryOperation, UnaryOperation};
 use super::bindings::{BindingInherit, BindingKeyValue, LetIn, With};
 use super::collection::{List, AttrSet};
 use super::control::IfThenElse;
-use super::function::{Function, FunctionApplication};
-use super::property::PropertyAccess;
-use super::string::{IndentedString, NixString};
+use super::function::{Function, FunctionApplication, FunctionHead};
+use super::property::{HasAttribute, PropertyAccess};
+use super::string::{IndentedString, NixString, PartInterpolation, PartRaw};
 use super::nix_errors::{Assert, Throw};
+use serde::{Deserialize, Serialize};
 
+use super::super::core::operators::precedence;
+use super::super::core::{Span, Trivia};
 use super::super::errors::RenderError;
 
-#[derive(Clone, Debug)]
+/// Serialized with a `kind` field naming the variant next to the node's own
+/// fields; see `docs/parser/json.md`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind")]
 pub enum Expression {
     Integer(Integer),
     Float(Float),
@@ -19,6 +27,9 @@ pub enum Expression {
     Boolean(Boolean),
     Null(Null),
     Path(Path),
+    SearchNixPath(SearchNixPath),
+    Uri(Uri),
+    UnaryOperation(UnaryOperation),
     BinaryOperation(BinaryOperation),
     List(List),
     AttrSet(AttrSet),
@@ -26,18 +37,27 @@ pub enum Expression {
     Function(Function),
     FunctionApplication(FunctionApplication),
     PropertyAccess(PropertyAccess),
+    HasAttribute(HasAttribute),
     NixString(NixString),
     IndentedString(IndentedString),
+    PartRaw(PartRaw),
+    PartInterpolation(PartInterpolation),
     BindingInherit(BindingInherit),
     BindingKeyValue(BindingKeyValue),
     With(With),
     LetIn(LetIn),
     Assert(Assert),
     Throw(Throw),
+    Error(Error),
 }
 
 impl Expression {
     pub fn render(&self) -> Result<String, RenderError> {
+        Ok(self.trivia().render(self.render_node()?))
+    }
+
+    /// Renders the node without its leading and trailing comments.
+    fn render_node(&self) -> Result<String, RenderError> {
         match self {
             Expression::Integer(x) => Ok(x.render()),
             Expression::Float(x) => Ok(x.render()),
@@ -45,7 +65,10 @@ impl Expression {
             Expression::LiteralString(x) => Ok(x.render()),
             Expression::Boolean(x) => Ok(x.render()),
             Expression::Null(x) => Ok(x.render()),
-            Expression::Path(x) => Ok(x.render()),
+            Expression::Path(x) => x.render(),
+            Expression::SearchNixPath(x) => Ok(x.render()),
+            Expression::Uri(x) => Ok(x.render()),
+            Expression::UnaryOperation(x) => x.render(),
             Expression::BinaryOperation(x) => x.render(),
             Expression::AttrSet(x) => x.render(),
             Expression::List(x) => x.render(),
@@ -53,14 +76,270 @@ impl Expression {
             Expression::Function(x) => x.render(),
             Expression::FunctionApplication(x) => x.render(),
             Expression::PropertyAccess(x) => x.render(),
+            Expression::HasAttribute(x) => x.render(),
             Expression::NixString(x) => x.render(),
             Expression::IndentedString(x) => x.render(),
+            Expression::PartRaw(x) => Ok(x.render()),
+            Expression::PartInterpolation(x) => x.render(),
             Expression::With(x) => x.render(),
             Expression::BindingInherit(x) => x.render(),
             Expression::BindingKeyValue(x) => x.render(),
             Expression::LetIn(x) => x.render(),
             Expression::Assert(x) => x.render(),
             Expression::Throw(x) => x.render(),
+            Expression::Error(x) => x.render(),
+        }
+    }
+
+    /// How loosely the rendered expression binds, on the scale of
+    /// [`Operator::precedence`](super::super::core::operators::Operator::precedence).
+    /// An expression needs parentheses wherever something binding tighter is
+    /// expected.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            // A negative literal built in code reads back as a negation.
+            Expression::Integer(x) if x.lexeme.starts_with('-') => 3,
+            Expression::Float(x) if x.lexeme.starts_with('-') => 3,
+            Expression::UnaryOperation(x) => x.operator.precedence(),
+            Expression::BinaryOperation(x) => x.operator.precedence(),
+            Expression::PropertyAccess(_) => precedence::SELECT,
+            Expression::FunctionApplication(_) | Expression::Throw(_) => precedence::APPLICATION,
+            Expression::HasAttribute(_) => precedence::HAS_ATTRIBUTE,
+            Expression::Function(_)
+            | Expression::LetIn(_)
+            | Expression::With(_)
+            | Expression::IfThenElse(_)
+            | Expression::Assert(_) => precedence::LOWEST,
+            _ => precedence::ATOM,
+        }
+    }
+
+    /// Renders the expression in a position that takes at most precedence
+    /// `max` without parentheses.
+    /// Comments stay outside the parentheses, where they were written.
+    pub fn render_operand(&self, max: u8) -> Result<String, RenderError> {
+        if self.precedence() > max {
+            Ok(self.trivia().render(format!("({})", self.render_node()?)))
+        } else {
+            self.render()
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Integer(x) => x.span.clone(),
+            Expression::Float(x) => x.span.clone(),
+            Expression::Identifier(x) => x.span.clone(),
+            Expression::LiteralString(x) => x.span.clone(),
+            Expression::Boolean(x) => x.span.clone(),
+            Expression::Null(x) => x.span.clone(),
+            Expression::Path(x) => x.span.clone(),
+            Expression::SearchNixPath(x) => x.span.clone(),
+            Expression::Uri(x) => x.span.clone(),
+            Expression::UnaryOperation(x) => x.span.clone(),
+            Expression::BinaryOperation(x) => x.span.clone(),
+            Expression::AttrSet(x) => x.span.clone(),
+            Expression::List(x) => x.span.clone(),
+            Expression::IfThenElse(x) => x.span.clone(),
+            Expression::Function(x) => x.span.clone(),
+            Expression::FunctionApplication(x) => x.span.clone(),
+            Expression::PropertyAccess(x) => x.span.clone(),
+            Expression::HasAttribute(x) => x.span.clone(),
+            Expression::NixString(x) => x.span.clone(),
+            Expression::IndentedString(x) => x.span.clone(),
+            Expression::PartRaw(x) => x.span.clone(),
+            Expression::PartInterpolation(x) => x.span.clone(),
+            Expression::With(x) => x.span.clone(),
+            Expression::BindingInherit(x) => x.span.clone(),
+            Expression::BindingKeyValue(x) => x.span.clone(),
+            Expression::LetIn(x) => x.span.clone(),
+            Expression::Assert(x) => x.span.clone(),
+            Expression::Throw(x) => x.span.clone(),
+            Expression::Error(x) => x.span().clone(),
+        }
+    }
+
+    pub fn trivia(&self) -> &Trivia {
+        match self {
+            Expression::Integer(x) => &x.trivia,
+            Expression::Float(x) => &x.trivia,
+            Expression::Identifier(x) => &x.trivia,
+            Expression::LiteralString(x) => &x.trivia,
+            Expression::Boolean(x) => &x.trivia,
+            Expression::Null(x) => &x.trivia,
+            Expression::Path(x) => &x.trivia,
+            Expression::SearchNixPath(x) => &x.trivia,
+            Expression::Uri(x) => &x.trivia,
+            Expression::UnaryOperation(x) => &x.trivia,
+            Expression::BinaryOperation(x) => &x.trivia,
+            Expression::AttrSet(x) => &x.trivia,
+            Expression::List(x) => &x.trivia,
+            Expression::IfThenElse(x) => &x.trivia,
+            Expression::Function(x) => &x.trivia,
+            Expression::FunctionApplication(x) => &x.trivia,
+            Expression::PropertyAccess(x) => &x.trivia,
+            Expression::HasAttribute(x) => &x.trivia,
+            Expression::NixString(x) => &x.trivia,
+            Expression::IndentedString(x) => &x.trivia,
+            Expression::PartRaw(x) => &x.trivia,
+            Expression::PartInterpolation(x) => &x.trivia,
+            Expression::With(x) => &x.trivia,
+            Expression::BindingInherit(x) => &x.trivia,
+            Expression::BindingKeyValue(x) => &x.trivia,
+            Expression::LetIn(x) => &x.trivia,
+            Expression::Assert(x) => &x.trivia,
+            Expression::Throw(x) => &x.trivia,
+            Expression::Error(x) => &x.trivia,
+        }
+    }
+
+    pub fn trivia_mut(&mut self) -> &mut Trivia {
+        match self {
+            Expression::Integer(x) => &mut x.trivia,
+            Expression::Float(x) => &mut x.trivia,
+            Expression::Identifier(x) => &mut x.trivia,
+            Expression::LiteralString(x) => &mut x.trivia,
+            Expression::Boolean(x) => &mut x.trivia,
+            Expression::Null(x) => &mut x.trivia,
+            Expression::Path(x) => &mut x.trivia,
+            Expression::SearchNixPath(x) => &mut x.trivia,
+            Expression::Uri(x) => &mut x.trivia,
+            Expression::UnaryOperation(x) => &mut x.trivia,
+            Expression::BinaryOperation(x) => &mut x.trivia,
+            Expression::AttrSet(x) => &mut x.trivia,
+            Expression::List(x) => &mut x.trivia,
+            Expression::IfThenElse(x) => &mut x.trivia,
+            Expression::Function(x) => &mut x.trivia,
+            Expression::FunctionApplication(x) => &mut x.trivia,
+            Expression::PropertyAccess(x) => &mut x.trivia,
+            Expression::HasAttribute(x) => &mut x.trivia,
+            Expression::NixString(x) => &mut x.trivia,
+            Expression::IndentedString(x) => &mut x.trivia,
+            Expression::PartRaw(x) => &mut x.trivia,
+            Expression::PartInterpolation(x) => &mut x.trivia,
+            Expression::With(x) => &mut x.trivia,
+            Expression::BindingInherit(x) => &mut x.trivia,
+            Expression::BindingKeyValue(x) => &mut x.trivia,
+            Expression::LetIn(x) => &mut x.trivia,
+            Expression::Assert(x) => &mut x.trivia,
+            Expression::Throw(x) => &mut x.trivia,
+            Expression::Error(x) => &mut x.trivia,
+        }
+    }
+
+    /// Direct sub-expressions in source order. Attribute path segments and
+    /// destructured argument defaults are included; function heads and
+    /// operators are not expressions and are skipped.
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Integer(_)
+            | Expression::Float(_)
+            | Expression::Identifier(_)
+            | Expression::LiteralString(_)
+            | Expression::Boolean(_)
+            | Expression::Null(_)
+            | Expression::SearchNixPath(_)
+            | Expression::Uri(_)
+            | Expression::PartRaw(_)
+            | Expression::Error(_) => Vec::new(),
+            Expression::Path(x) => x.parts.iter().collect(),
+            Expression::UnaryOperation(x) => vec![&*x.expression],
+            Expression::BinaryOperation(x) => vec![&*x.left, &*x.right],
+            Expression::AttrSet(x) => x.bindings.iter().collect(),
+            Expression::List(x) => x.elements.iter().collect(),
+            Expression::IfThenElse(x) => vec![&*x.predicate, &*x.then, &*x.else_],
+            Expression::Function(x) => {
+                let mut children = Vec::new();
+                if let FunctionHead::FunctionHeadDestructured(head) = &x.head {
+                    children.extend(head.arguments.iter().filter_map(|a| a.default.as_ref()));
+                }
+                children.push(&*x.body);
+                children
+            }
+            Expression::FunctionApplication(x) => {
+                std::iter::once(&*x.function).chain(x.arguments.iter()).collect()
+            }
+            Expression::PropertyAccess(x) => {
+                let mut children = vec![&*x.expression];
+                children.extend(x.attribute_path.attributes.iter());
+                children.extend(x.default.as_deref());
+                children
+            }
+            Expression::HasAttribute(x) => {
+                std::iter::once(&*x.expression).chain(x.attribute_path.attributes.iter()).collect()
+            }
+            Expression::NixString(x) => x.parts.iter().collect(),
+            Expression::IndentedString(x) => x.parts.iter().collect(),
+            Expression::PartInterpolation(x) => vec![&*x.expression],
+            Expression::With(x) => vec![&*x.scope, &*x.body],
+            Expression::BindingInherit(x) => {
+                x.from_.as_deref().into_iter().chain(x.attributes.iter()).collect()
+            }
+            Expression::BindingKeyValue(x) => {
+                x.from_.attributes.iter().chain(std::iter::once(&*x.to)).collect()
+            }
+            Expression::LetIn(x) => {
+                x.bindings.iter().chain(std::iter::once(&*x.target)).collect()
+            }
+            Expression::Assert(x) => vec![&*x.condition, &*x.body],
+            Expression::Throw(x) => vec![&*x.message],
+        }
+    }
+
+    /// Mutable counterpart of [`Expression::children`], in the same order.
+    pub fn children_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Expression::Integer(_)
+            | Expression::Float(_)
+            | Expression::Identifier(_)
+            | Expression::LiteralString(_)
+            | Expression::Boolean(_)
+            | Expression::Null(_)
+            | Expression::SearchNixPath(_)
+            | Expression::Uri(_)
+            | Expression::PartRaw(_)
+            | Expression::Error(_) => Vec::new(),
+            Expression::Path(x) => x.parts.iter_mut().collect(),
+            Expression::UnaryOperation(x) => vec![&mut *x.expression],
+            Expression::BinaryOperation(x) => vec![&mut *x.left, &mut *x.right],
+            Expression::AttrSet(x) => x.bindings.iter_mut().collect(),
+            Expression::List(x) => x.elements.iter_mut().collect(),
+            Expression::IfThenElse(x) => vec![&mut *x.predicate, &mut *x.then, &mut *x.else_],
+            Expression::Function(x) => {
+                let mut children = Vec::new();
+                if let FunctionHead::FunctionHeadDestructured(head) = &mut x.head {
+                    children.extend(head.arguments.iter_mut().filter_map(|a| a.default.as_mut()));
+                }
+                children.push(&mut *x.body);
+                children
+            }
+            Expression::FunctionApplication(x) => {
+                std::iter::once(&mut *x.function).chain(x.arguments.iter_mut()).collect()
+            }
+            Expression::PropertyAccess(x) => {
+                let mut children = vec![&mut *x.expression];
+                children.extend(x.attribute_path.attributes.iter_mut());
+                children.extend(x.default.as_deref_mut());
+                children
+            }
+            Expression::HasAttribute(x) => std::iter::once(&mut *x.expression)
+                .chain(x.attribute_path.attributes.iter_mut())
+                .collect(),
+            Expression::NixString(x) => x.parts.iter_mut().collect(),
+            Expression::IndentedString(x) => x.parts.iter_mut().collect(),
+            Expression::PartInterpolation(x) => vec![&mut *x.expression],
+            Expression::With(x) => vec![&mut *x.scope, &mut *x.body],
+            Expression::BindingInherit(x) => {
+                x.from_.as_deref_mut().into_iter().chain(x.attributes.iter_mut()).collect()
+            }
+            Expression::BindingKeyValue(x) => {
+                x.from_.attributes.iter_mut().chain(std::iter::once(&mut *x.to)).collect()
+            }
+            Expression::LetIn(x) => {
+                x.bindings.iter_mut().chain(std::iter::once(&mut *x.target)).collect()
+            }
+            Expression::Assert(x) => vec![&mut *x.condition, &mut *x.body],
+            Expression::Throw(x) => vec![&mut *x.message],
         }
     }
 }
@@ -1,58 +1,81 @@
-use super::super::core::position::{Position, Span};
+use super::super::core::operators::precedence;
+use super::super::core::position::Span;
+use super::super::core::trivia::Trivia;
 use super::super::errors::RenderError;
 use super::super::core::identifier::Identifier;
 use super::expression::Expression;  
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+/// One formal of a destructured head, `name` or `name ? default`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FunctionHeadDestructuredArgument {
-    pub identifier: String,
+    pub identifier: Identifier,
     pub default: Option<Expression>,
+    /// Covers the name and the default, if any.
+    #[serde(default)]
+    pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl FunctionHeadDestructuredArgument {
-    pub fn new(identifier: String, default: Option<Expression>) -> Self {
+    pub fn new_span(identifier: Identifier, default: Option<Expression>, span: Span) -> Self {
         Self {
             identifier,
             default,
+            span,
+            trivia: Trivia::default(),
         }
     }
 
+    pub fn new(identifier: Identifier, default: Option<Expression>) -> Self {
+        Self::new_span(identifier, default, Span::detached())
+    }
+
     pub fn debug(&self) -> String {
         format!(
-            "FunctionHeadDestructuredArgument(identifier='{}', default={:?})",
+            "FunctionHeadDestructuredArgument(identifier={:?}, default={:?})",
             self.identifier, self.default
         )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
-        match &self.default {
+        let rendered = match &self.default {
             Some(expr) => {
                 let rendered = expr.render()?; // unwraps Ok or returns Err
-                Ok(format!("{} ? {}", self.identifier, rendered))
+                format!("{} ? {}", self.identifier.render(), rendered)
             }
-            None => Ok(self.identifier.clone()),
-        }
+            None => self.identifier.render(),
+        };
+        Ok(self.trivia.render(rendered))
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FunctionHeadDestructured {
     pub ellipsis: bool,
-    pub identifier: Identifier,
+    /// The name bound to the whole argument with `@`, if any.
+    pub identifier: Option<Identifier>,
+    /// Whether the `@` binding is written before the pattern (`args@{ ... }`)
+    /// rather than after it (`{ ... }@args`).
+    pub identifier_before: bool,
     pub arguments: Vec<FunctionHeadDestructuredArgument>,
+    #[serde(default)]
     pub span: Span,
 }
 
 impl FunctionHeadDestructured {
     pub fn new_span(
         ellipsis: bool,
-        identifier: Identifier,
+        identifier: Option<Identifier>,
+        identifier_before: bool,
         arguments: Vec<FunctionHeadDestructuredArgument>,
         span: Span,
     ) -> Self {
         Self {
             ellipsis,
             identifier,
+            identifier_before,
             arguments,
             span,
         }
@@ -60,33 +83,53 @@ impl FunctionHeadDestructured {
 
     pub fn new(
         ellipsis: bool,
-        identifier: Identifier,
+        identifier: Option<Identifier>,
         arguments: Vec<FunctionHeadDestructuredArgument>,
     ) -> Self {
-        Self {
+        Self::new_span(
             ellipsis,
             identifier,
+            false,
             arguments,
-            span: Span::new(Position::new(1, 1), Position::new(1, 1)),
-        }
+            Span::detached(),
+        )
     }
 
     pub fn debug(&self) -> String {
-        format!("FunctionHeadDestructured(ellipsis={})", self.ellipsis)
+        format!(
+            "FunctionHeadDestructured(ellipsis={}, identifier={:?})",
+            self.ellipsis, self.identifier
+        )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
-        let mut result: String = String::new();
-        for argument in &self.arguments {
-            result.push_str(&argument.render()?)
+        let mut arguments = self
+            .arguments
+            .iter()
+            .map(|a| a.render())
+            .collect::<Result<Vec<_>, _>>()?;
+        if self.ellipsis {
+            arguments.push("...".to_string());
         }
-        Ok(result)
+        let pattern = if arguments.is_empty() {
+            "{ }".to_string()
+        } else {
+            format!("{{ {} }}", arguments.join(", "))
+        };
+        Ok(match &self.identifier {
+            Some(identifier) if self.identifier_before => {
+                format!("{}@{}", identifier.render(), pattern)
+            }
+            Some(identifier) => format!("{}@{}", pattern, identifier.render()),
+            None => pattern,
+        })
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FunctionHeadSimple {
     pub identifier: Identifier,
+    #[serde(default)]
     pub span: Span,
 }
 
@@ -98,7 +141,7 @@ impl FunctionHeadSimple {
     pub fn new(identifier: Identifier) -> Self {
         Self {
             identifier,
-            span: Span::new(Position::new(1, 1), Position::new(1, 1)),
+            span: Span::detached(),
         }
     }
 
@@ -114,6 +157,8 @@ impl FunctionHeadSimple {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind")]
 pub enum FunctionHead {
     FunctionHeadSimple(FunctionHeadSimple),
     FunctionHeadDestructured(FunctionHeadDestructured),
@@ -133,30 +178,41 @@ impl FunctionHead {
             FunctionHead::FunctionHeadDestructured(x) => x.debug(),
         }
     }
+
+    pub fn span(&self) -> Span {
+        match self {
+            FunctionHead::FunctionHeadSimple(x) => x.span.clone(),
+            FunctionHead::FunctionHeadDestructured(x) => x.span.clone(),
+        }
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Function {
-    pub head: Box<Expression>,
+    pub head: FunctionHead,
     pub body: Box<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl Function {
-    pub fn new_span(head: Expression, body: Expression, span: Span) -> Self {
+    pub fn new_span(head: FunctionHead, body: Expression, span: Span) -> Self {
         Self {
-            head: Box::new(head),
+            head,
             body: Box::new(body),
             span,
+            trivia: Trivia::default(),
         }
     }
 
-    pub fn new(head: Expression, body: Expression) -> Self {
-        Self {
-            head: Box::new(head),
-            body: Box::new(body),
-            span: Span::new(Position::new(1, 1), Position::new(1, 1)),
-        }
+    pub fn new(head: FunctionHead, body: Expression) -> Self {
+        Self::new_span(
+            head,
+            body,
+            Span::detached(),
+        )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
@@ -166,15 +222,18 @@ impl Function {
     }
 
     pub fn debug(&self) -> String {
-        format!("Function({:?}, {:?})", self.head, self.body)
+        format!("Function({}, {:?})", self.head.debug(), self.body)
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FunctionApplication {
     pub function: Box<Expression>,
     pub arguments: Vec<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl FunctionApplication {
@@ -183,24 +242,23 @@ impl FunctionApplication {
             function: Box::new(function),
             arguments,
             span,
+            trivia: Trivia::default(),
         }
     }
 
     pub fn new(function: Expression, arguments: Vec<Expression>) -> Self {
-        Self {
-            function: Box::new(function),
-            arguments,
-            span: Span::new(Position::new(1, 1), Position::new(1, 1)),
-        }
+        Self::new_span(function, arguments, Span::detached())
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
         let args = self
             .arguments
             .iter()
-            .map(|a| a.render())
+            .map(|a| a.render_operand(precedence::SELECT))
             .collect::<Result<Vec<_>, _>>()?;
-        let function = self.function.render()?;
+        // A nested application is parenthesized so that it reads back as the
+        // same tree rather than as one application with more arguments.
+        let function = self.function.render_operand(precedence::SELECT)?;
         Ok(format!("{} {}", function, args.join(" ")))
     }
 
@@ -8,7 +8,7 @@ pub mod string;
 pub mod nix_errors;
 pub mod bindings;
 
-pub use operations::BinaryOperation;
+pub use operations::{BinaryOperation, UnaryOperation};
 pub use collection::{List, AttrSet};
 pub use control::IfThenElse;
 pub use expression::Expression;
@@ -16,9 +16,9 @@ pub use function::{
     Function, FunctionApplication, FunctionHead, FunctionHeadDestructured,
     FunctionHeadDestructuredArgument, FunctionHeadSimple
 };
-pub use property::{PropertyAccess};
+pub use property::{AttrPath, HasAttribute, PropertyAccess};
 pub use string::{
-    NixString, IndentedString
+    NixString, IndentedString, PartRaw, PartInterpolation, CookedPart
 };
 pub use nix_errors::{Assert, Throw};
 pub use bindings::{BindingInherit, BindingKeyValue, LetIn, With};
@@ -1,45 +1,50 @@
-use super::super::core::position::{Position, Span};
+use super::super::core::operators::precedence;
+use super::super::core::position::Span;
+use super::super::core::trivia::Trivia;
 use super::super::errors::{RenderError};
 use super::expression::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Assert {
     pub condition: Box<Expression>,
-    pub message: Option<Box<Expression>>,
+    pub body: Box<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl Assert {
-    pub fn new_span(condition: Expression, message: Option<Expression>, span: Span) -> Self {
+    pub fn new_span(condition: Expression, body: Expression, span: Span) -> Self {
         Self {
             condition: Box::new(condition),
-            message: message.map(Box::new),
+            body: Box::new(body),
             span,
+            trivia: Trivia::default(),
         }
     }
 
-    pub fn new(condition: Expression, message: Option<Expression>) -> Self {
+    pub fn new(condition: Expression, body: Expression) -> Self {
         Self::new_span(
             condition,
-            message,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            body,
+            Span::detached(),
         )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
-        let condition = self.condition.render()?;
-        let message = match &self.message {
-            Some(msg) => format!(" with message {}", msg.render()?),
-            None => String::new(),
-        };
-        Ok(format!("assert {}{}", condition, message))
+        Ok(format!("assert {}; {}", self.condition.render()?, self.body.render()?))
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Throw {
     pub message: Box<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl Throw {
@@ -47,17 +52,18 @@ impl Throw {
         Self {
             message: Box::new(message),
             span,
+            trivia: Trivia::default(),
         }
     }
 
     pub fn new(message: Expression) -> Self {
         Self::new_span(
             message,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            Span::detached(),
         )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
-        Ok(format!("throw {}", self.message.render()?))
+        Ok(format!("throw {}", self.message.render_operand(precedence::SELECT)?))
     }
 }
\ No newline at end of file
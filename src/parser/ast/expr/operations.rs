@@ -1,14 +1,21 @@
-use super::super::core::operators::Operator;
-use super::super::core::position::{Position, Span};
-use super::super::errors::{RenderError, SyntaxError};
+use super::super::core::operators::{Associativity, Operator};
+use super::super::core::position::Span;
+use super::super::core::trivia::Trivia;
+use super::super::errors::RenderError;
+use crate::diagnostic::{Diagnostic, codes};
 use super::expression::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "BinaryOperationFields")]
 pub struct BinaryOperation {
     pub left: Box<Expression>,
     pub operator: Operator,
     pub right: Box<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl BinaryOperation {
@@ -17,17 +24,20 @@ impl BinaryOperation {
         operator: Operator,
         right: Expression,
         span: Span,
-    ) -> Result<Self, SyntaxError> {
+    ) -> Result<Self, Box<Diagnostic>> {
         if !operator.is_binary() {
-            return Err(SyntaxError {
-                message: format!("Tried to create binary operation using {:?}", operator),
-            });
+            return Err(Diagnostic::error(
+                codes::INVALID_OPERATOR,
+                format!("Tried to create binary operation using {:?}", operator),
+                span,
+            ).into());
         }
         Ok(Self {
             left: Box::new(left),
             operator,
             right: Box::new(right),
             span,
+            trivia: Trivia::default(),
         })
     }
 
@@ -35,21 +45,29 @@ impl BinaryOperation {
         left: Expression,
         operator: Operator,
         right: Expression,
-    ) -> Result<Self, SyntaxError> {
+    ) -> Result<Self, Box<Diagnostic>> {
         Self::new_span(
             left,
             operator,
             right,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            Span::detached(),
         )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
+        // An operand of the same precedence only goes without parentheses
+        // on the side the operator groups towards.
+        let precedence = self.operator.precedence();
+        let (left, right) = match self.operator.associativity() {
+            Associativity::Left => (precedence, precedence - 1),
+            Associativity::Right => (precedence - 1, precedence),
+            Associativity::None => (precedence - 1, precedence - 1),
+        };
         Ok(format!(
-            "({} {} {})",
-            self.left.render()?,
+            "{} {} {}",
+            self.left.render_operand(left)?,
             self.operator.render(),
-            self.right.render()?
+            self.right.render_operand(right)?
         ))
     }
 
@@ -61,11 +79,15 @@ impl BinaryOperation {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "UnaryOperationFields")]
 pub struct UnaryOperation {
     pub operator: Operator,
     pub expression: Box<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl UnaryOperation {
@@ -73,24 +95,27 @@ impl UnaryOperation {
         operator: Operator,
         expression: Expression,
         span: Span,
-    ) -> Result<Self, SyntaxError> {
+    ) -> Result<Self, Box<Diagnostic>> {
         if !operator.is_unary() {
-            return Err(SyntaxError {
-                message: format!("Tried to create unary operation with {:?}", operator),
-            });
+            return Err(Diagnostic::error(
+                codes::INVALID_OPERATOR,
+                format!("Tried to create unary operation with {:?}", operator),
+                span,
+            ).into());
         }
         Ok(Self {
             operator,
             expression: Box::new(expression),
             span,
+            trivia: Trivia::default(),
         })
     }
 
-    pub fn new(operator: Operator, expression: Expression) -> Result<Self, SyntaxError> {
+    pub fn new(operator: Operator, expression: Expression) -> Result<Self, Box<Diagnostic>> {
         Self::new_span(
             operator,
             expression,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            Span::detached(),
         )
     }
 
@@ -98,7 +123,7 @@ impl UnaryOperation {
         Ok(format!(
             "{}{}",
             self.operator.render(),
-            self.expression.render()?
+            self.expression.render_operand(self.operator.precedence())?
         ))
     }
 
@@ -106,3 +131,51 @@ impl UnaryOperation {
         format!("UnaryOperation({:?}, {:?})", self.operator, self.expression)
     }
 }
+
+/// The fields of an imported `BinaryOperation`, checked by
+/// [`BinaryOperation::new_span`] like operations built in code.
+#[derive(Deserialize)]
+struct BinaryOperationFields {
+    left: Box<Expression>,
+    operator: Operator,
+    right: Box<Expression>,
+    #[serde(default)]
+    span: Span,
+    #[serde(default)]
+    trivia: Trivia,
+}
+
+impl TryFrom<BinaryOperationFields> for BinaryOperation {
+    type Error = String;
+
+    fn try_from(fields: BinaryOperationFields) -> Result<Self, String> {
+        let mut operation =
+            Self::new_span(*fields.left, fields.operator, *fields.right, fields.span)
+                .map_err(|e| e.message)?;
+        operation.trivia = fields.trivia;
+        Ok(operation)
+    }
+}
+
+/// The fields of an imported `UnaryOperation`, checked by
+/// [`UnaryOperation::new_span`].
+#[derive(Deserialize)]
+struct UnaryOperationFields {
+    operator: Operator,
+    expression: Box<Expression>,
+    #[serde(default)]
+    span: Span,
+    #[serde(default)]
+    trivia: Trivia,
+}
+
+impl TryFrom<UnaryOperationFields> for UnaryOperation {
+    type Error = String;
+
+    fn try_from(fields: UnaryOperationFields) -> Result<Self, String> {
+        let mut operation = Self::new_span(fields.operator, *fields.expression, fields.span)
+            .map_err(|e| e.message)?;
+        operation.trivia = fields.trivia;
+        Ok(operation)
+    }
+}
@@ -1,19 +1,25 @@
-use super::super::core::position::{Position, Span};
+use super::super::core::operators::precedence;
+use super::super::core::position::Span;
+use super::super::core::trivia::Trivia;
 use super::super::errors::RenderError;
 use super::expression::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PropertyAccess {
     pub expression: Box<Expression>,
-    pub attribute_path: Vec<Expression>,
+    pub attribute_path: AttrPath,
     pub default: Option<Box<Expression>>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl PropertyAccess {
     pub fn new_span(
         expression: Expression,
-        attribute_path: Vec<Expression>,
+        attribute_path: AttrPath,
         default: Option<Expression>,
         span: Span,
     ) -> Self {
@@ -22,37 +28,34 @@ impl PropertyAccess {
             attribute_path,
             default: default.map(Box::new),
             span,
+            trivia: Trivia::default(),
         }
     }
 
     pub fn new(
         expression: Expression,
-        attribute_path: Vec<Expression>,
+        attribute_path: AttrPath,
         default: Option<Expression>,
     ) -> Self {
         Self::new_span(
             expression,
             attribute_path,
             default,
-            Span::new(Position::new(1, 1), Position::new(1, 1)),
+            Span::detached(),
         )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
-        let path = self
-            .attribute_path
-            .iter()
-            .map(|e| e.render())
-            .collect::<Result<Vec<_>, _>>()?
-            .join(".");
+        let expression = self.expression.render_operand(precedence::ATOM)?;
+        let path = self.attribute_path.render()?;
         match &self.default {
             Some(default) => Ok(format!(
-                "{}.?{}.or({})",
-                self.expression.render()?,
+                "{}.{} or {}",
+                expression,
                 path,
-                default.render()?
+                default.render_operand(precedence::SELECT)?
             )),
-            None => Ok(format!("{}.{}", self.expression.render()?, path)),
+            None => Ok(format!("{}.{}", expression, path)),
         }
     }
 
@@ -62,4 +65,85 @@ impl PropertyAccess {
             self.expression, self.attribute_path, self.default
         )
     }
-}
\ No newline at end of file
+}
+
+/// The has-attribute test `expression ? a.b.c`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HasAttribute {
+    pub expression: Box<Expression>,
+    pub attribute_path: AttrPath,
+    #[serde(default)]
+    pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
+}
+
+impl HasAttribute {
+    pub fn new_span(expression: Expression, attribute_path: AttrPath, span: Span) -> Self {
+        Self {
+            expression: Box::new(expression),
+            attribute_path,
+            span,
+            trivia: Trivia::default(),
+        }
+    }
+
+    pub fn new(expression: Expression, attribute_path: AttrPath) -> Self {
+        Self::new_span(
+            expression,
+            attribute_path,
+            Span::detached(),
+        )
+    }
+
+    pub fn render(&self) -> Result<String, RenderError> {
+        Ok(format!(
+            "{} ? {}",
+            // `a ? b ? c` is rejected, so a nested test needs parentheses.
+            self.expression.render_operand(precedence::HAS_ATTRIBUTE - 1)?,
+            self.attribute_path.render()?
+        ))
+    }
+
+    pub fn debug(&self) -> String {
+        format!(
+            "HasAttribute(expr={:?}, path={:?})",
+            self.expression, self.attribute_path
+        )
+    }
+}
+
+/// A dotted attribute path such as `a.b."c".${d}`. Each attribute is an
+/// `Identifier`, a `NixString` or a `PartInterpolation`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttrPath {
+    pub attributes: Vec<Expression>,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl AttrPath {
+    pub fn new_span(attributes: Vec<Expression>, span: Span) -> Self {
+        Self { attributes, span }
+    }
+
+    pub fn new(attributes: Vec<Expression>) -> Self {
+        Self::new_span(
+            attributes,
+            Span::detached(),
+        )
+    }
+
+    pub fn render(&self) -> Result<String, RenderError> {
+        Ok(self
+            .attributes
+            .iter()
+            .map(|a| a.render())
+            .collect::<Result<Vec<_>, _>>()?
+            .join("."))
+    }
+
+    pub fn debug(&self) -> String {
+        format!("AttrPath({:?})", self.attributes)
+    }
+}
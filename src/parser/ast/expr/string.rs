@@ -1,29 +1,48 @@
-use super::super::core::position::{Position, Span};
+use super::super::core::position::Span;
+use super::super::core::trivia::Trivia;
 use super::super::errors::RenderError;
 use super::expression::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NixString {
     pub parts: Vec<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl NixString {
     pub fn new_span(parts: Vec<Expression>, span: Span) -> Self {
-        Self { parts, span }
+        Self { parts, span, trivia: Trivia::default() }
     }
 
     pub fn new(parts: Vec<Expression>) -> Self {
-        Self::new_span(parts, Span::new(Position::new(1, 1), Position::new(1, 1)))
+        Self::new_span(parts, Span::detached())
+    }
+
+    /// The value of the string with escapes resolved, or `None` if it
+    /// contains interpolations.
+    pub fn value(&self) -> Option<String> {
+        let mut value = String::new();
+        for part in &self.parts {
+            match part {
+                Expression::PartRaw(raw) => value.push_str(&raw.value()),
+                _ => return None,
+            }
+        }
+        Some(value)
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
-        Ok(self
+        let parts = self
             .parts
             .iter()
             .map(|p| p.render())
             .collect::<Result<Vec<_>, _>>()?
-            .join(""))
+            .join("");
+        Ok(format!("\"{}\"", parts))
     }
 
     pub fn debug(&self) -> String {
@@ -31,22 +50,27 @@ impl NixString {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct IndentedString {
     pub parts: Vec<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl IndentedString {
     pub fn new_span(parts: Vec<Expression>, span: Span) -> Self {
-        Self { parts, span }
+        Self { parts, span, trivia: Trivia::default() }
     }
 
     pub fn new(parts: Vec<Expression>) -> Self {
-        Self::new_span(parts, Span::new(Position::new(1, 1), Position::new(1, 1)))
+        Self::new_span(parts, Span::detached())
     }
 
-    pub fn render(&self) -> Result<String, RenderError> {
+    /// The source text between the opening and closing `''`, with escapes
+    /// and indentation left exactly as written.
+    pub fn raw(&self) -> Result<String, RenderError> {
         Ok(self
             .parts
             .iter()
@@ -55,15 +79,269 @@ impl IndentedString {
             .join(""))
     }
 
+    /// The parts of the string as Nix evaluates them: escapes resolved, the
+    /// common indentation stripped and a trailing whitespace-only line
+    /// removed. Adjacent text is merged into a single `CookedPart::Text`.
+    pub fn cooked(&self) -> Vec<CookedPart<'_>> {
+        let mut tokens = Vec::new();
+        for (index, part) in self.parts.iter().enumerate() {
+            match part {
+                Expression::PartRaw(raw) => {
+                    let mut content = raw.content.as_str();
+                    if index == 0 {
+                        // The opening `''` swallows the rest of its line when
+                        // that line is blank.
+                        let blank = content.trim_start_matches(' ');
+                        if let Some(rest) = blank.strip_prefix('\n') {
+                            content = rest;
+                        }
+                    }
+                    lex_indented_text(content, &mut tokens);
+                }
+                Expression::PartInterpolation(interpolation) => {
+                    tokens.push(IndentedToken::Interpolation(&interpolation.expression))
+                }
+                other => tokens.push(IndentedToken::Interpolation(other)),
+            }
+        }
+        strip_indentation(tokens)
+    }
+
+    /// The evaluated value of the string, or `None` if it contains
+    /// interpolations.
+    pub fn value(&self) -> Option<String> {
+        let mut value = String::new();
+        for part in self.cooked() {
+            match part {
+                CookedPart::Text(text) => value.push_str(&text),
+                CookedPart::Interpolation(_) => return None,
+            }
+        }
+        Some(value)
+    }
+
+    pub fn render(&self) -> Result<String, RenderError> {
+        Ok(format!("''{}''", self.raw()?))
+    }
+
     pub fn debug(&self) -> String {
         format!("IndentedString({:?})", self.parts)
     }
 }
 
+/// A piece of a string after escapes and indentation have been processed.
 #[derive(Clone, Debug)]
+pub enum CookedPart<'a> {
+    Text(String),
+    Interpolation(&'a Expression),
+}
+
+enum IndentedToken<'a> {
+    /// Literal text; its leading spaces count as indentation.
+    Text(String),
+    /// Text produced by an escape sequence, which never counts as indentation.
+    Escaped(String),
+    Interpolation(&'a Expression),
+}
+
+/// Splits raw indented string text into the same tokens the Nix lexer
+/// produces, so that indentation stripping treats escapes like Nix does.
+fn lex_indented_text(content: &str, tokens: &mut Vec<IndentedToken<'_>>) {
+    let chars: Vec<char> = content.chars().collect();
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        let escaped = match (chars[i], next) {
+            ('\'', Some('\'')) => match chars.get(i + 2) {
+                Some('\'') => Some(("''".to_string(), 3)),
+                Some('$') => Some(("$".to_string(), 3)),
+                Some('\\') => chars.get(i + 3).map(|c| {
+                    let unescaped = match c {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        other => *other,
+                    };
+                    (unescaped.to_string(), 4)
+                }),
+                _ => None,
+            },
+            ('$', None | Some('{') | Some('\'')) | ('\'', None | Some('$')) => {
+                Some((chars[i].to_string(), 1))
+            }
+            _ => None,
+        };
+        match escaped {
+            Some((value, length)) => {
+                if !text.is_empty() {
+                    tokens.push(IndentedToken::Text(std::mem::take(&mut text)));
+                }
+                tokens.push(IndentedToken::Escaped(value));
+                i += length;
+            }
+            None => {
+                // `$` and `'` are lexed together with the character after
+                // them, so that character never starts an escape.
+                let length = match (chars[i], next) {
+                    ('$' | '\'', Some(_)) => 2,
+                    _ => 1,
+                };
+                text.extend(&chars[i..i + length]);
+                i += length;
+            }
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(IndentedToken::Text(text));
+    }
+}
+
+/// Port of `stripIndentation` from the Nix parser.
+fn strip_indentation(tokens: Vec<IndentedToken<'_>>) -> Vec<CookedPart<'_>> {
+    // Find the minimum indentation, ignoring whitespace-only lines.
+    let mut at_start_of_line = true;
+    let mut min_indent = usize::MAX;
+    let mut current_indent = 0;
+    for token in &tokens {
+        let IndentedToken::Text(text) = token else {
+            if at_start_of_line {
+                at_start_of_line = false;
+                min_indent = min_indent.min(current_indent);
+            }
+            continue;
+        };
+        for c in text.chars() {
+            if at_start_of_line {
+                match c {
+                    ' ' => current_indent += 1,
+                    '\n' => current_indent = 0,
+                    _ => {
+                        at_start_of_line = false;
+                        min_indent = min_indent.min(current_indent);
+                    }
+                }
+            } else if c == '\n' {
+                at_start_of_line = true;
+                current_indent = 0;
+            }
+        }
+    }
+
+    // Drop that much indentation from the start of every line.
+    let mut parts: Vec<CookedPart> = Vec::new();
+    let mut at_start_of_line = true;
+    let mut dropped = 0;
+    let count = tokens.len();
+    for (index, token) in tokens.into_iter().enumerate() {
+        let text = match token {
+            IndentedToken::Interpolation(expression) => {
+                at_start_of_line = false;
+                dropped = 0;
+                parts.push(CookedPart::Interpolation(expression));
+                continue;
+            }
+            IndentedToken::Text(text) | IndentedToken::Escaped(text) => text,
+        };
+        let mut stripped = String::new();
+        for c in text.chars() {
+            if at_start_of_line {
+                match c {
+                    ' ' => {
+                        if dropped >= min_indent {
+                            stripped.push(c);
+                        }
+                        dropped += 1;
+                    }
+                    '\n' => {
+                        dropped = 0;
+                        stripped.push(c);
+                    }
+                    _ => {
+                        at_start_of_line = false;
+                        dropped = 0;
+                        stripped.push(c);
+                    }
+                }
+            } else {
+                stripped.push(c);
+                if c == '\n' {
+                    at_start_of_line = true;
+                }
+            }
+        }
+
+        // Remove the final line if it only contains spaces.
+        if index + 1 == count
+            && let Some(newline) = stripped.rfind('\n')
+            && stripped[newline + 1..].chars().all(|c| c == ' ')
+        {
+            stripped.truncate(newline + 1);
+        }
+
+        match parts.last_mut() {
+            Some(CookedPart::Text(previous)) => previous.push_str(&stripped),
+            _ => parts.push(CookedPart::Text(stripped)),
+        }
+    }
+    parts
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartRaw {
+    pub content: String,
+    #[serde(default)]
+    pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
+}
+
+impl PartRaw {
+    pub fn new_span(content: String, span: Span) -> Self {
+        Self { content, span, trivia: Trivia::default() }
+    }
+
+    pub fn new(content: String) -> Self {
+        Self::new_span(content, Span::detached())
+    }
+
+    /// The text of this part with all escape sequences resolved, as it would
+    /// appear inside a double-quoted string.
+    pub fn value(&self) -> String {
+        let mut value = String::with_capacity(self.content.len());
+        let mut chars = self.content.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('r') => value.push('\r'),
+                Some('t') => value.push('\t'),
+                Some(other) => value.push(other),
+                None => value.push('\\'),
+            }
+        }
+        value
+    }
+
+    pub fn render(&self) -> String {
+        self.content.clone()
+    }
+
+    pub fn debug(&self) -> String {
+        format!("PartRaw({:?})", self.content)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PartInterpolation {
     pub expression: Box<Expression>,
+    #[serde(default)]
     pub span: Span,
+    #[serde(default, skip_serializing_if = "Trivia::is_empty")]
+    pub trivia: Trivia,
 }
 
 impl PartInterpolation {
@@ -71,14 +349,15 @@ impl PartInterpolation {
         Self {
             expression: Box::new(expression),
             span,
+            trivia: Trivia::default(),
         }
     }
 
-    pub fn new(expression: Expression, span: Span) -> Self {
-        Self {
-            expression: Box::new(expression),
-            span: Span::new(Position::new(1, 1), Position::new(1, 1)),
-        }
+    pub fn new(expression: Expression) -> Self {
+        Self::new_span(
+            expression,
+            Span::detached(),
+        )
     }
 
     pub fn render(&self) -> Result<String, RenderError> {
@@ -0,0 +1,482 @@
+//! Tree rewriting by value.
+//!
+//! Each `fold_*` method takes a node and returns its replacement; the default
+//! `walk_*` functions rebuild the node from its folded children and keep its
+//! span and trivia. Override `fold_expression` to replace a node with one of a
+//! different kind.
+
+use super::core::{
+    Boolean, Error, Float, Identifier, Integer, LiteralString, Null, Path, SearchNixPath, Uri,
+};
+use super::expr::{
+    Assert, AttrPath, AttrSet, BinaryOperation, BindingInherit, BindingKeyValue, Expression,
+    Function, FunctionApplication, FunctionHead, FunctionHeadDestructured,
+    FunctionHeadDestructuredArgument, FunctionHeadSimple, HasAttribute, IfThenElse, IndentedString,
+    LetIn, List, NixString, PartInterpolation, PartRaw, PropertyAccess, Throw, UnaryOperation,
+    With,
+};
+
+pub trait Fold {
+    fn fold_expression(&mut self, node: Expression) -> Expression {
+        walk_expression(self, node)
+    }
+
+    fn fold_integer(&mut self, node: Integer) -> Integer {
+        walk_integer(self, node)
+    }
+
+    fn fold_float(&mut self, node: Float) -> Float {
+        walk_float(self, node)
+    }
+
+    fn fold_identifier(&mut self, node: Identifier) -> Identifier {
+        walk_identifier(self, node)
+    }
+
+    fn fold_literal_string(&mut self, node: LiteralString) -> LiteralString {
+        walk_literal_string(self, node)
+    }
+
+    fn fold_boolean(&mut self, node: Boolean) -> Boolean {
+        walk_boolean(self, node)
+    }
+
+    fn fold_null(&mut self, node: Null) -> Null {
+        walk_null(self, node)
+    }
+
+    fn fold_path(&mut self, node: Path) -> Path {
+        walk_path(self, node)
+    }
+
+    fn fold_search_nix_path(&mut self, node: SearchNixPath) -> SearchNixPath {
+        walk_search_nix_path(self, node)
+    }
+
+    fn fold_uri(&mut self, node: Uri) -> Uri {
+        walk_uri(self, node)
+    }
+
+    fn fold_unary_operation(&mut self, node: UnaryOperation) -> UnaryOperation {
+        walk_unary_operation(self, node)
+    }
+
+    fn fold_binary_operation(&mut self, node: BinaryOperation) -> BinaryOperation {
+        walk_binary_operation(self, node)
+    }
+
+    fn fold_list(&mut self, node: List) -> List {
+        walk_list(self, node)
+    }
+
+    fn fold_attr_set(&mut self, node: AttrSet) -> AttrSet {
+        walk_attr_set(self, node)
+    }
+
+    fn fold_if_then_else(&mut self, node: IfThenElse) -> IfThenElse {
+        walk_if_then_else(self, node)
+    }
+
+    fn fold_function(&mut self, node: Function) -> Function {
+        walk_function(self, node)
+    }
+
+    fn fold_function_application(&mut self, node: FunctionApplication) -> FunctionApplication {
+        walk_function_application(self, node)
+    }
+
+    fn fold_property_access(&mut self, node: PropertyAccess) -> PropertyAccess {
+        walk_property_access(self, node)
+    }
+
+    fn fold_has_attribute(&mut self, node: HasAttribute) -> HasAttribute {
+        walk_has_attribute(self, node)
+    }
+
+    fn fold_nix_string(&mut self, node: NixString) -> NixString {
+        walk_nix_string(self, node)
+    }
+
+    fn fold_indented_string(&mut self, node: IndentedString) -> IndentedString {
+        walk_indented_string(self, node)
+    }
+
+    fn fold_part_raw(&mut self, node: PartRaw) -> PartRaw {
+        walk_part_raw(self, node)
+    }
+
+    fn fold_part_interpolation(&mut self, node: PartInterpolation) -> PartInterpolation {
+        walk_part_interpolation(self, node)
+    }
+
+    fn fold_binding_inherit(&mut self, node: BindingInherit) -> BindingInherit {
+        walk_binding_inherit(self, node)
+    }
+
+    fn fold_binding_key_value(&mut self, node: BindingKeyValue) -> BindingKeyValue {
+        walk_binding_key_value(self, node)
+    }
+
+    fn fold_with(&mut self, node: With) -> With {
+        walk_with(self, node)
+    }
+
+    fn fold_let_in(&mut self, node: LetIn) -> LetIn {
+        walk_let_in(self, node)
+    }
+
+    fn fold_assert(&mut self, node: Assert) -> Assert {
+        walk_assert(self, node)
+    }
+
+    fn fold_throw(&mut self, node: Throw) -> Throw {
+        walk_throw(self, node)
+    }
+
+    fn fold_error(&mut self, node: Error) -> Error {
+        walk_error(self, node)
+    }
+
+    fn fold_attr_path(&mut self, node: AttrPath) -> AttrPath {
+        walk_attr_path(self, node)
+    }
+
+    fn fold_function_head(&mut self, node: FunctionHead) -> FunctionHead {
+        walk_function_head(self, node)
+    }
+}
+
+pub fn walk_expression<F: Fold + ?Sized>(folder: &mut F, node: Expression) -> Expression {
+    match node {
+        Expression::Integer(x) => Expression::Integer(folder.fold_integer(x)),
+        Expression::Float(x) => Expression::Float(folder.fold_float(x)),
+        Expression::Identifier(x) => Expression::Identifier(folder.fold_identifier(x)),
+        Expression::LiteralString(x) => Expression::LiteralString(folder.fold_literal_string(x)),
+        Expression::Boolean(x) => Expression::Boolean(folder.fold_boolean(x)),
+        Expression::Null(x) => Expression::Null(folder.fold_null(x)),
+        Expression::Path(x) => Expression::Path(folder.fold_path(x)),
+        Expression::SearchNixPath(x) => Expression::SearchNixPath(folder.fold_search_nix_path(x)),
+        Expression::Uri(x) => Expression::Uri(folder.fold_uri(x)),
+        Expression::UnaryOperation(x) => Expression::UnaryOperation(folder.fold_unary_operation(x)),
+        Expression::BinaryOperation(x) => {
+            Expression::BinaryOperation(folder.fold_binary_operation(x))
+        }
+        Expression::List(x) => Expression::List(folder.fold_list(x)),
+        Expression::AttrSet(x) => Expression::AttrSet(folder.fold_attr_set(x)),
+        Expression::IfThenElse(x) => Expression::IfThenElse(folder.fold_if_then_else(x)),
+        Expression::Function(x) => Expression::Function(folder.fold_function(x)),
+        Expression::FunctionApplication(x) => {
+            Expression::FunctionApplication(folder.fold_function_application(x))
+        }
+        Expression::PropertyAccess(x) => Expression::PropertyAccess(folder.fold_property_access(x)),
+        Expression::HasAttribute(x) => Expression::HasAttribute(folder.fold_has_attribute(x)),
+        Expression::NixString(x) => Expression::NixString(folder.fold_nix_string(x)),
+        Expression::IndentedString(x) => Expression::IndentedString(folder.fold_indented_string(x)),
+        Expression::PartRaw(x) => Expression::PartRaw(folder.fold_part_raw(x)),
+        Expression::PartInterpolation(x) => {
+            Expression::PartInterpolation(folder.fold_part_interpolation(x))
+        }
+        Expression::BindingInherit(x) => Expression::BindingInherit(folder.fold_binding_inherit(x)),
+        Expression::BindingKeyValue(x) => {
+            Expression::BindingKeyValue(folder.fold_binding_key_value(x))
+        }
+        Expression::With(x) => Expression::With(folder.fold_with(x)),
+        Expression::LetIn(x) => Expression::LetIn(folder.fold_let_in(x)),
+        Expression::Assert(x) => Expression::Assert(folder.fold_assert(x)),
+        Expression::Throw(x) => Expression::Throw(folder.fold_throw(x)),
+        Expression::Error(x) => Expression::Error(folder.fold_error(x)),
+    }
+}
+
+pub fn walk_integer<F: Fold + ?Sized>(_folder: &mut F, node: Integer) -> Integer {
+    node
+}
+
+pub fn walk_float<F: Fold + ?Sized>(_folder: &mut F, node: Float) -> Float {
+    node
+}
+
+pub fn walk_identifier<F: Fold + ?Sized>(_folder: &mut F, node: Identifier) -> Identifier {
+    node
+}
+
+pub fn walk_literal_string<F: Fold + ?Sized>(
+    _folder: &mut F,
+    node: LiteralString,
+) -> LiteralString {
+    node
+}
+
+pub fn walk_boolean<F: Fold + ?Sized>(_folder: &mut F, node: Boolean) -> Boolean {
+    node
+}
+
+pub fn walk_null<F: Fold + ?Sized>(_folder: &mut F, node: Null) -> Null {
+    node
+}
+
+pub fn walk_path<F: Fold + ?Sized>(folder: &mut F, node: Path) -> Path {
+    Path {
+        parts: node
+            .parts
+            .into_iter()
+            .map(|child| folder.fold_expression(child))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn walk_search_nix_path<F: Fold + ?Sized>(
+    _folder: &mut F,
+    node: SearchNixPath,
+) -> SearchNixPath {
+    node
+}
+
+pub fn walk_uri<F: Fold + ?Sized>(_folder: &mut F, node: Uri) -> Uri {
+    node
+}
+
+pub fn walk_unary_operation<F: Fold + ?Sized>(
+    folder: &mut F,
+    node: UnaryOperation,
+) -> UnaryOperation {
+    UnaryOperation {
+        expression: Box::new(folder.fold_expression(*node.expression)),
+        ..node
+    }
+}
+
+pub fn walk_binary_operation<F: Fold + ?Sized>(
+    folder: &mut F,
+    node: BinaryOperation,
+) -> BinaryOperation {
+    BinaryOperation {
+        left: Box::new(folder.fold_expression(*node.left)),
+        right: Box::new(folder.fold_expression(*node.right)),
+        ..node
+    }
+}
+
+pub fn walk_list<F: Fold + ?Sized>(folder: &mut F, node: List) -> List {
+    List {
+        elements: node
+            .elements
+            .into_iter()
+            .map(|child| folder.fold_expression(child))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn walk_attr_set<F: Fold + ?Sized>(folder: &mut F, node: AttrSet) -> AttrSet {
+    AttrSet {
+        bindings: node
+            .bindings
+            .into_iter()
+            .map(|child| folder.fold_expression(child))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn walk_if_then_else<F: Fold + ?Sized>(folder: &mut F, node: IfThenElse) -> IfThenElse {
+    IfThenElse {
+        predicate: Box::new(folder.fold_expression(*node.predicate)),
+        then: Box::new(folder.fold_expression(*node.then)),
+        else_: Box::new(folder.fold_expression(*node.else_)),
+        ..node
+    }
+}
+
+pub fn walk_function<F: Fold + ?Sized>(folder: &mut F, node: Function) -> Function {
+    Function {
+        head: folder.fold_function_head(node.head),
+        body: Box::new(folder.fold_expression(*node.body)),
+        ..node
+    }
+}
+
+pub fn walk_function_application<F: Fold + ?Sized>(
+    folder: &mut F,
+    node: FunctionApplication,
+) -> FunctionApplication {
+    FunctionApplication {
+        function: Box::new(folder.fold_expression(*node.function)),
+        arguments: node
+            .arguments
+            .into_iter()
+            .map(|child| folder.fold_expression(child))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn walk_property_access<F: Fold + ?Sized>(
+    folder: &mut F,
+    node: PropertyAccess,
+) -> PropertyAccess {
+    PropertyAccess {
+        expression: Box::new(folder.fold_expression(*node.expression)),
+        attribute_path: folder.fold_attr_path(node.attribute_path),
+        default: node
+            .default
+            .map(|child| Box::new(folder.fold_expression(*child))),
+        ..node
+    }
+}
+
+pub fn walk_has_attribute<F: Fold + ?Sized>(folder: &mut F, node: HasAttribute) -> HasAttribute {
+    HasAttribute {
+        expression: Box::new(folder.fold_expression(*node.expression)),
+        attribute_path: folder.fold_attr_path(node.attribute_path),
+        ..node
+    }
+}
+
+pub fn walk_nix_string<F: Fold + ?Sized>(folder: &mut F, node: NixString) -> NixString {
+    NixString {
+        parts: node
+            .parts
+            .into_iter()
+            .map(|child| folder.fold_expression(child))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn walk_indented_string<F: Fold + ?Sized>(
+    folder: &mut F,
+    node: IndentedString,
+) -> IndentedString {
+    IndentedString {
+        parts: node
+            .parts
+            .into_iter()
+            .map(|child| folder.fold_expression(child))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn walk_part_raw<F: Fold + ?Sized>(_folder: &mut F, node: PartRaw) -> PartRaw {
+    node
+}
+
+pub fn walk_part_interpolation<F: Fold + ?Sized>(
+    folder: &mut F,
+    node: PartInterpolation,
+) -> PartInterpolation {
+    PartInterpolation {
+        expression: Box::new(folder.fold_expression(*node.expression)),
+        ..node
+    }
+}
+
+pub fn walk_binding_inherit<F: Fold + ?Sized>(
+    folder: &mut F,
+    node: BindingInherit,
+) -> BindingInherit {
+    BindingInherit {
+        from_: node
+            .from_
+            .map(|child| Box::new(folder.fold_expression(*child))),
+        attributes: node
+            .attributes
+            .into_iter()
+            .map(|child| folder.fold_expression(child))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn walk_binding_key_value<F: Fold + ?Sized>(
+    folder: &mut F,
+    node: BindingKeyValue,
+) -> BindingKeyValue {
+    BindingKeyValue {
+        from_: folder.fold_attr_path(node.from_),
+        to: Box::new(folder.fold_expression(*node.to)),
+        ..node
+    }
+}
+
+pub fn walk_with<F: Fold + ?Sized>(folder: &mut F, node: With) -> With {
+    With {
+        scope: Box::new(folder.fold_expression(*node.scope)),
+        body: Box::new(folder.fold_expression(*node.body)),
+        ..node
+    }
+}
+
+pub fn walk_let_in<F: Fold + ?Sized>(folder: &mut F, node: LetIn) -> LetIn {
+    LetIn {
+        bindings: node
+            .bindings
+            .into_iter()
+            .map(|child| folder.fold_expression(child))
+            .collect(),
+        target: Box::new(folder.fold_expression(*node.target)),
+        ..node
+    }
+}
+
+pub fn walk_assert<F: Fold + ?Sized>(folder: &mut F, node: Assert) -> Assert {
+    Assert {
+        condition: Box::new(folder.fold_expression(*node.condition)),
+        body: Box::new(folder.fold_expression(*node.body)),
+        ..node
+    }
+}
+
+pub fn walk_throw<F: Fold + ?Sized>(folder: &mut F, node: Throw) -> Throw {
+    Throw {
+        message: Box::new(folder.fold_expression(*node.message)),
+        ..node
+    }
+}
+
+pub fn walk_error<F: Fold + ?Sized>(_folder: &mut F, node: Error) -> Error {
+    node
+}
+
+pub fn walk_attr_path<F: Fold + ?Sized>(folder: &mut F, node: AttrPath) -> AttrPath {
+    AttrPath {
+        attributes: node
+            .attributes
+            .into_iter()
+            .map(|child| folder.fold_expression(child))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn walk_function_head<F: Fold + ?Sized>(folder: &mut F, node: FunctionHead) -> FunctionHead {
+    match node {
+        FunctionHead::FunctionHeadSimple(head) => {
+            FunctionHead::FunctionHeadSimple(FunctionHeadSimple {
+                identifier: folder.fold_identifier(head.identifier),
+                ..head
+            })
+        }
+        FunctionHead::FunctionHeadDestructured(head) => {
+            FunctionHead::FunctionHeadDestructured(FunctionHeadDestructured {
+                identifier: head
+                    .identifier
+                    .map(|identifier| folder.fold_identifier(identifier)),
+                arguments: head
+                    .arguments
+                    .into_iter()
+                    .map(|argument| FunctionHeadDestructuredArgument {
+                        identifier: folder.fold_identifier(argument.identifier),
+                        default: argument
+                            .default
+                            .map(|default| folder.fold_expression(default)),
+                        ..argument
+                    })
+                    .collect(),
+                ..head
+            })
+        }
+    }
+}
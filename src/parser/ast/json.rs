@@ -0,0 +1,67 @@
+//! JSON export and import of the AST.
+//!
+//! The format is described in `docs/parser/json.md`. Every document carries
+//! [`JSON_SCHEMA_VERSION`]; it is bumped whenever the shape of a node changes
+//! so that consumers can reject documents they do not understand.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::expr::Expression;
+
+/// The version written by [`to_json`] and the only one [`from_json`] accepts.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// The top-level object of an exported document.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Document {
+    pub version: u32,
+    pub expressions: Vec<Expression>,
+}
+
+impl Document {
+    pub fn new(expressions: Vec<Expression>) -> Self {
+        Self { version: JSON_SCHEMA_VERSION, expressions }
+    }
+}
+
+#[derive(Serialize)]
+struct DocumentRef<'a> {
+    version: u32,
+    expressions: &'a [Expression],
+}
+
+impl<'a> DocumentRef<'a> {
+    fn new(expressions: &'a [Expression]) -> Self {
+        Self { version: JSON_SCHEMA_VERSION, expressions }
+    }
+}
+
+pub fn to_json(expressions: &[Expression]) -> serde_json::Result<String> {
+    serde_json::to_string(&DocumentRef::new(expressions))
+}
+
+pub fn to_json_pretty(expressions: &[Expression]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&DocumentRef::new(expressions))
+}
+
+/// Reads a document written by [`to_json`], or by an external tool following
+/// the same schema. Spans and trivia may be left out; nodes without a span
+/// are detached.
+pub fn from_json(json: &str) -> serde_json::Result<Vec<Expression>> {
+    // The version is checked before the nodes so that a document from a newer
+    // schema is reported as such rather than as some unknown field.
+    let value: Value = serde_json::from_str(json)?;
+    match value.get("version").and_then(Value::as_u64) {
+        Some(version) if version == u64::from(JSON_SCHEMA_VERSION) => {}
+        Some(version) => {
+            return Err(serde_json::Error::custom(format!(
+                "unsupported schema version {version}, expected {JSON_SCHEMA_VERSION}"
+            )));
+        }
+        None => return Err(serde_json::Error::custom("missing schema `version`")),
+    }
+    let document: Document = serde_json::from_value(value)?;
+    Ok(document.expressions)
+}
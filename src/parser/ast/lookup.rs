@@ -0,0 +1,73 @@
+//! Finding the node under a cursor.
+
+use super::core::Span;
+use super::expr::{Expression, FunctionHead, FunctionHeadDestructuredArgument};
+
+/// The innermost expression at some offset, with the chain of expressions
+/// enclosing it.
+#[derive(Clone, Debug)]
+pub struct NodePath<'a> {
+    pub node: &'a Expression,
+    /// Enclosing expressions, from the root down to the direct parent.
+    pub ancestors: Vec<&'a Expression>,
+    /// The formal of a function head covering the offset, if any. `node` is
+    /// then its function, or lies inside the formal's default.
+    pub formal: Option<&'a FunctionHeadDestructuredArgument>,
+}
+
+impl<'a> NodePath<'a> {
+    pub fn parent(&self) -> Option<&'a Expression> {
+        self.ancestors.last().copied()
+    }
+
+    /// The node followed by its ancestors, innermost first.
+    pub fn iter(&self) -> impl Iterator<Item = &'a Expression> + '_ {
+        std::iter::once(self.node).chain(self.ancestors.iter().rev().copied())
+    }
+}
+
+/// Finds the innermost expression covering the byte `offset`.
+///
+/// A node covers the offsets from its start up to and including its end, so
+/// a cursor placed right after `foo` still finds `foo`. When one node ends
+/// where the next begins, the one starting there wins. Nodes built in code
+/// rather than parsed have no position and are never found.
+pub fn node_at(ast: &[Expression], offset: usize) -> Option<NodePath<'_>> {
+    let mut node = covering(ast.iter(), offset, Expression::span)?;
+    let mut ancestors = Vec::new();
+    let mut formal = None;
+    loop {
+        if let Expression::Function(function) = node
+            && let FunctionHead::FunctionHeadDestructured(head) = &function.head
+        {
+            formal = covering(head.arguments.iter(), offset, |a| a.span.clone()).or(formal);
+        }
+        let Some(child) = covering(node.children().into_iter(), offset, Expression::span) else {
+            break;
+        };
+        ancestors.push(node);
+        node = child;
+    }
+    Some(NodePath { node, ancestors, formal })
+}
+
+fn covering<'a, T>(
+    nodes: impl Iterator<Item = &'a T>,
+    offset: usize,
+    span: impl Fn(&T) -> Span,
+) -> Option<&'a T> {
+    let mut touching = None;
+    for node in nodes {
+        let span = span(node);
+        if span.is_detached() {
+            continue;
+        }
+        if span.start.offset <= offset && offset < span.end.offset {
+            return Some(node);
+        }
+        if span.end.offset == offset && touching.is_none() {
+            touching = Some(node);
+        }
+    }
+    touching
+}
@@ -1,7 +1,15 @@
 pub mod core;
 pub mod expr;
 pub mod errors;
+pub mod visit;
+pub mod fold;
+pub mod lookup;
+pub mod arena;
+pub mod json;
+pub mod eq;
 
 pub use errors::RenderError;
+pub use eq::SpannedEq;
+pub use lookup::{NodePath, node_at};
 pub use expr::{*};
 pub use core::{*};
\ No newline at end of file
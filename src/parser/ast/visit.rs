@@ -0,0 +1,695 @@
+//! Read-only and in-place traversal of the AST.
+//!
+//! Every node type has a `visit_*` method whose default implementation calls
+//! the matching `walk_*` function, which visits the node's children in source
+//! order. Override the methods for the nodes you care about and call `walk_*`
+//! from them to keep descending.
+
+use super::core::{
+    Boolean, Error, Float, Identifier, Integer, LiteralString, Null, Path, SearchNixPath, Uri,
+};
+use super::expr::{
+    Assert, AttrPath, AttrSet, BinaryOperation, BindingInherit, BindingKeyValue, Expression,
+    Function, FunctionApplication, FunctionHead, HasAttribute, IfThenElse, IndentedString, LetIn,
+    List, NixString, PartInterpolation, PartRaw, PropertyAccess, Throw, UnaryOperation, With,
+};
+
+pub trait Visitor {
+    fn visit_expression(&mut self, node: &Expression) {
+        walk_expression(self, node);
+    }
+
+    fn visit_integer(&mut self, node: &Integer) {
+        walk_integer(self, node);
+    }
+
+    fn visit_float(&mut self, node: &Float) {
+        walk_float(self, node);
+    }
+
+    fn visit_identifier(&mut self, node: &Identifier) {
+        walk_identifier(self, node);
+    }
+
+    fn visit_literal_string(&mut self, node: &LiteralString) {
+        walk_literal_string(self, node);
+    }
+
+    fn visit_boolean(&mut self, node: &Boolean) {
+        walk_boolean(self, node);
+    }
+
+    fn visit_null(&mut self, node: &Null) {
+        walk_null(self, node);
+    }
+
+    fn visit_path(&mut self, node: &Path) {
+        walk_path(self, node);
+    }
+
+    fn visit_search_nix_path(&mut self, node: &SearchNixPath) {
+        walk_search_nix_path(self, node);
+    }
+
+    fn visit_uri(&mut self, node: &Uri) {
+        walk_uri(self, node);
+    }
+
+    fn visit_unary_operation(&mut self, node: &UnaryOperation) {
+        walk_unary_operation(self, node);
+    }
+
+    fn visit_binary_operation(&mut self, node: &BinaryOperation) {
+        walk_binary_operation(self, node);
+    }
+
+    fn visit_list(&mut self, node: &List) {
+        walk_list(self, node);
+    }
+
+    fn visit_attr_set(&mut self, node: &AttrSet) {
+        walk_attr_set(self, node);
+    }
+
+    fn visit_if_then_else(&mut self, node: &IfThenElse) {
+        walk_if_then_else(self, node);
+    }
+
+    fn visit_function(&mut self, node: &Function) {
+        walk_function(self, node);
+    }
+
+    fn visit_function_application(&mut self, node: &FunctionApplication) {
+        walk_function_application(self, node);
+    }
+
+    fn visit_property_access(&mut self, node: &PropertyAccess) {
+        walk_property_access(self, node);
+    }
+
+    fn visit_has_attribute(&mut self, node: &HasAttribute) {
+        walk_has_attribute(self, node);
+    }
+
+    fn visit_nix_string(&mut self, node: &NixString) {
+        walk_nix_string(self, node);
+    }
+
+    fn visit_indented_string(&mut self, node: &IndentedString) {
+        walk_indented_string(self, node);
+    }
+
+    fn visit_part_raw(&mut self, node: &PartRaw) {
+        walk_part_raw(self, node);
+    }
+
+    fn visit_part_interpolation(&mut self, node: &PartInterpolation) {
+        walk_part_interpolation(self, node);
+    }
+
+    fn visit_binding_inherit(&mut self, node: &BindingInherit) {
+        walk_binding_inherit(self, node);
+    }
+
+    fn visit_binding_key_value(&mut self, node: &BindingKeyValue) {
+        walk_binding_key_value(self, node);
+    }
+
+    fn visit_with(&mut self, node: &With) {
+        walk_with(self, node);
+    }
+
+    fn visit_let_in(&mut self, node: &LetIn) {
+        walk_let_in(self, node);
+    }
+
+    fn visit_assert(&mut self, node: &Assert) {
+        walk_assert(self, node);
+    }
+
+    fn visit_throw(&mut self, node: &Throw) {
+        walk_throw(self, node);
+    }
+
+    fn visit_error(&mut self, node: &Error) {
+        walk_error(self, node);
+    }
+
+    fn visit_attr_path(&mut self, node: &AttrPath) {
+        walk_attr_path(self, node);
+    }
+
+    fn visit_function_head(&mut self, node: &FunctionHead) {
+        walk_function_head(self, node);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, node: &Expression) {
+    match node {
+        Expression::Integer(x) => visitor.visit_integer(x),
+        Expression::Float(x) => visitor.visit_float(x),
+        Expression::Identifier(x) => visitor.visit_identifier(x),
+        Expression::LiteralString(x) => visitor.visit_literal_string(x),
+        Expression::Boolean(x) => visitor.visit_boolean(x),
+        Expression::Null(x) => visitor.visit_null(x),
+        Expression::Path(x) => visitor.visit_path(x),
+        Expression::SearchNixPath(x) => visitor.visit_search_nix_path(x),
+        Expression::Uri(x) => visitor.visit_uri(x),
+        Expression::UnaryOperation(x) => visitor.visit_unary_operation(x),
+        Expression::BinaryOperation(x) => visitor.visit_binary_operation(x),
+        Expression::List(x) => visitor.visit_list(x),
+        Expression::AttrSet(x) => visitor.visit_attr_set(x),
+        Expression::IfThenElse(x) => visitor.visit_if_then_else(x),
+        Expression::Function(x) => visitor.visit_function(x),
+        Expression::FunctionApplication(x) => visitor.visit_function_application(x),
+        Expression::PropertyAccess(x) => visitor.visit_property_access(x),
+        Expression::HasAttribute(x) => visitor.visit_has_attribute(x),
+        Expression::NixString(x) => visitor.visit_nix_string(x),
+        Expression::IndentedString(x) => visitor.visit_indented_string(x),
+        Expression::PartRaw(x) => visitor.visit_part_raw(x),
+        Expression::PartInterpolation(x) => visitor.visit_part_interpolation(x),
+        Expression::BindingInherit(x) => visitor.visit_binding_inherit(x),
+        Expression::BindingKeyValue(x) => visitor.visit_binding_key_value(x),
+        Expression::With(x) => visitor.visit_with(x),
+        Expression::LetIn(x) => visitor.visit_let_in(x),
+        Expression::Assert(x) => visitor.visit_assert(x),
+        Expression::Throw(x) => visitor.visit_throw(x),
+        Expression::Error(x) => visitor.visit_error(x),
+    }
+}
+
+pub fn walk_integer<V: Visitor + ?Sized>(_visitor: &mut V, _node: &Integer) {}
+
+pub fn walk_float<V: Visitor + ?Sized>(_visitor: &mut V, _node: &Float) {}
+
+pub fn walk_identifier<V: Visitor + ?Sized>(_visitor: &mut V, _node: &Identifier) {}
+
+pub fn walk_literal_string<V: Visitor + ?Sized>(_visitor: &mut V, _node: &LiteralString) {}
+
+pub fn walk_boolean<V: Visitor + ?Sized>(_visitor: &mut V, _node: &Boolean) {}
+
+pub fn walk_null<V: Visitor + ?Sized>(_visitor: &mut V, _node: &Null) {}
+
+pub fn walk_path<V: Visitor + ?Sized>(visitor: &mut V, node: &Path) {
+    for child in node.parts.iter() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_search_nix_path<V: Visitor + ?Sized>(_visitor: &mut V, _node: &SearchNixPath) {}
+
+pub fn walk_uri<V: Visitor + ?Sized>(_visitor: &mut V, _node: &Uri) {}
+
+pub fn walk_unary_operation<V: Visitor + ?Sized>(visitor: &mut V, node: &UnaryOperation) {
+    visitor.visit_expression(&node.expression);
+}
+
+pub fn walk_binary_operation<V: Visitor + ?Sized>(visitor: &mut V, node: &BinaryOperation) {
+    visitor.visit_expression(&node.left);
+    visitor.visit_expression(&node.right);
+}
+
+pub fn walk_list<V: Visitor + ?Sized>(visitor: &mut V, node: &List) {
+    for child in node.elements.iter() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_attr_set<V: Visitor + ?Sized>(visitor: &mut V, node: &AttrSet) {
+    for child in node.bindings.iter() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_if_then_else<V: Visitor + ?Sized>(visitor: &mut V, node: &IfThenElse) {
+    visitor.visit_expression(&node.predicate);
+    visitor.visit_expression(&node.then);
+    visitor.visit_expression(&node.else_);
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, node: &Function) {
+    visitor.visit_function_head(&node.head);
+    visitor.visit_expression(&node.body);
+}
+
+pub fn walk_function_application<V: Visitor + ?Sized>(visitor: &mut V, node: &FunctionApplication) {
+    visitor.visit_expression(&node.function);
+    for child in node.arguments.iter() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_property_access<V: Visitor + ?Sized>(visitor: &mut V, node: &PropertyAccess) {
+    visitor.visit_expression(&node.expression);
+    visitor.visit_attr_path(&node.attribute_path);
+    if let Some(child) = &node.default {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_has_attribute<V: Visitor + ?Sized>(visitor: &mut V, node: &HasAttribute) {
+    visitor.visit_expression(&node.expression);
+    visitor.visit_attr_path(&node.attribute_path);
+}
+
+pub fn walk_nix_string<V: Visitor + ?Sized>(visitor: &mut V, node: &NixString) {
+    for child in node.parts.iter() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_indented_string<V: Visitor + ?Sized>(visitor: &mut V, node: &IndentedString) {
+    for child in node.parts.iter() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_part_raw<V: Visitor + ?Sized>(_visitor: &mut V, _node: &PartRaw) {}
+
+pub fn walk_part_interpolation<V: Visitor + ?Sized>(visitor: &mut V, node: &PartInterpolation) {
+    visitor.visit_expression(&node.expression);
+}
+
+pub fn walk_binding_inherit<V: Visitor + ?Sized>(visitor: &mut V, node: &BindingInherit) {
+    if let Some(child) = &node.from_ {
+        visitor.visit_expression(child);
+    }
+    for child in node.attributes.iter() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_binding_key_value<V: Visitor + ?Sized>(visitor: &mut V, node: &BindingKeyValue) {
+    visitor.visit_attr_path(&node.from_);
+    visitor.visit_expression(&node.to);
+}
+
+pub fn walk_with<V: Visitor + ?Sized>(visitor: &mut V, node: &With) {
+    visitor.visit_expression(&node.scope);
+    visitor.visit_expression(&node.body);
+}
+
+pub fn walk_let_in<V: Visitor + ?Sized>(visitor: &mut V, node: &LetIn) {
+    for child in node.bindings.iter() {
+        visitor.visit_expression(child);
+    }
+    visitor.visit_expression(&node.target);
+}
+
+pub fn walk_assert<V: Visitor + ?Sized>(visitor: &mut V, node: &Assert) {
+    visitor.visit_expression(&node.condition);
+    visitor.visit_expression(&node.body);
+}
+
+pub fn walk_throw<V: Visitor + ?Sized>(visitor: &mut V, node: &Throw) {
+    visitor.visit_expression(&node.message);
+}
+
+pub fn walk_error<V: Visitor + ?Sized>(_visitor: &mut V, _node: &Error) {}
+
+pub fn walk_attr_path<V: Visitor + ?Sized>(visitor: &mut V, node: &AttrPath) {
+    for child in node.attributes.iter() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_function_head<V: Visitor + ?Sized>(visitor: &mut V, node: &FunctionHead) {
+    match node {
+        FunctionHead::FunctionHeadSimple(head) => visitor.visit_identifier(&head.identifier),
+        FunctionHead::FunctionHeadDestructured(head) => {
+            let (before, after) = if head.identifier_before {
+                (head.identifier.as_ref(), None)
+            } else {
+                (None, head.identifier.as_ref())
+            };
+            if let Some(identifier) = before {
+                visitor.visit_identifier(identifier);
+            }
+            for argument in head.arguments.iter() {
+                visitor.visit_identifier(&argument.identifier);
+                if let Some(default) = &argument.default {
+                    visitor.visit_expression(default);
+                }
+            }
+            if let Some(identifier) = after {
+                visitor.visit_identifier(identifier);
+            }
+        }
+    }
+}
+
+pub trait VisitorMut {
+    fn visit_expression(&mut self, node: &mut Expression) {
+        walk_expression_mut(self, node);
+    }
+
+    fn visit_integer(&mut self, node: &mut Integer) {
+        walk_integer_mut(self, node);
+    }
+
+    fn visit_float(&mut self, node: &mut Float) {
+        walk_float_mut(self, node);
+    }
+
+    fn visit_identifier(&mut self, node: &mut Identifier) {
+        walk_identifier_mut(self, node);
+    }
+
+    fn visit_literal_string(&mut self, node: &mut LiteralString) {
+        walk_literal_string_mut(self, node);
+    }
+
+    fn visit_boolean(&mut self, node: &mut Boolean) {
+        walk_boolean_mut(self, node);
+    }
+
+    fn visit_null(&mut self, node: &mut Null) {
+        walk_null_mut(self, node);
+    }
+
+    fn visit_path(&mut self, node: &mut Path) {
+        walk_path_mut(self, node);
+    }
+
+    fn visit_search_nix_path(&mut self, node: &mut SearchNixPath) {
+        walk_search_nix_path_mut(self, node);
+    }
+
+    fn visit_uri(&mut self, node: &mut Uri) {
+        walk_uri_mut(self, node);
+    }
+
+    fn visit_unary_operation(&mut self, node: &mut UnaryOperation) {
+        walk_unary_operation_mut(self, node);
+    }
+
+    fn visit_binary_operation(&mut self, node: &mut BinaryOperation) {
+        walk_binary_operation_mut(self, node);
+    }
+
+    fn visit_list(&mut self, node: &mut List) {
+        walk_list_mut(self, node);
+    }
+
+    fn visit_attr_set(&mut self, node: &mut AttrSet) {
+        walk_attr_set_mut(self, node);
+    }
+
+    fn visit_if_then_else(&mut self, node: &mut IfThenElse) {
+        walk_if_then_else_mut(self, node);
+    }
+
+    fn visit_function(&mut self, node: &mut Function) {
+        walk_function_mut(self, node);
+    }
+
+    fn visit_function_application(&mut self, node: &mut FunctionApplication) {
+        walk_function_application_mut(self, node);
+    }
+
+    fn visit_property_access(&mut self, node: &mut PropertyAccess) {
+        walk_property_access_mut(self, node);
+    }
+
+    fn visit_has_attribute(&mut self, node: &mut HasAttribute) {
+        walk_has_attribute_mut(self, node);
+    }
+
+    fn visit_nix_string(&mut self, node: &mut NixString) {
+        walk_nix_string_mut(self, node);
+    }
+
+    fn visit_indented_string(&mut self, node: &mut IndentedString) {
+        walk_indented_string_mut(self, node);
+    }
+
+    fn visit_part_raw(&mut self, node: &mut PartRaw) {
+        walk_part_raw_mut(self, node);
+    }
+
+    fn visit_part_interpolation(&mut self, node: &mut PartInterpolation) {
+        walk_part_interpolation_mut(self, node);
+    }
+
+    fn visit_binding_inherit(&mut self, node: &mut BindingInherit) {
+        walk_binding_inherit_mut(self, node);
+    }
+
+    fn visit_binding_key_value(&mut self, node: &mut BindingKeyValue) {
+        walk_binding_key_value_mut(self, node);
+    }
+
+    fn visit_with(&mut self, node: &mut With) {
+        walk_with_mut(self, node);
+    }
+
+    fn visit_let_in(&mut self, node: &mut LetIn) {
+        walk_let_in_mut(self, node);
+    }
+
+    fn visit_assert(&mut self, node: &mut Assert) {
+        walk_assert_mut(self, node);
+    }
+
+    fn visit_throw(&mut self, node: &mut Throw) {
+        walk_throw_mut(self, node);
+    }
+
+    fn visit_error(&mut self, node: &mut Error) {
+        walk_error_mut(self, node);
+    }
+
+    fn visit_attr_path(&mut self, node: &mut AttrPath) {
+        walk_attr_path_mut(self, node);
+    }
+
+    fn visit_function_head(&mut self, node: &mut FunctionHead) {
+        walk_function_head_mut(self, node);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Expression) {
+    match node {
+        Expression::Integer(x) => visitor.visit_integer(x),
+        Expression::Float(x) => visitor.visit_float(x),
+        Expression::Identifier(x) => visitor.visit_identifier(x),
+        Expression::LiteralString(x) => visitor.visit_literal_string(x),
+        Expression::Boolean(x) => visitor.visit_boolean(x),
+        Expression::Null(x) => visitor.visit_null(x),
+        Expression::Path(x) => visitor.visit_path(x),
+        Expression::SearchNixPath(x) => visitor.visit_search_nix_path(x),
+        Expression::Uri(x) => visitor.visit_uri(x),
+        Expression::UnaryOperation(x) => visitor.visit_unary_operation(x),
+        Expression::BinaryOperation(x) => visitor.visit_binary_operation(x),
+        Expression::List(x) => visitor.visit_list(x),
+        Expression::AttrSet(x) => visitor.visit_attr_set(x),
+        Expression::IfThenElse(x) => visitor.visit_if_then_else(x),
+        Expression::Function(x) => visitor.visit_function(x),
+        Expression::FunctionApplication(x) => visitor.visit_function_application(x),
+        Expression::PropertyAccess(x) => visitor.visit_property_access(x),
+        Expression::HasAttribute(x) => visitor.visit_has_attribute(x),
+        Expression::NixString(x) => visitor.visit_nix_string(x),
+        Expression::IndentedString(x) => visitor.visit_indented_string(x),
+        Expression::PartRaw(x) => visitor.visit_part_raw(x),
+        Expression::PartInterpolation(x) => visitor.visit_part_interpolation(x),
+        Expression::BindingInherit(x) => visitor.visit_binding_inherit(x),
+        Expression::BindingKeyValue(x) => visitor.visit_binding_key_value(x),
+        Expression::With(x) => visitor.visit_with(x),
+        Expression::LetIn(x) => visitor.visit_let_in(x),
+        Expression::Assert(x) => visitor.visit_assert(x),
+        Expression::Throw(x) => visitor.visit_throw(x),
+        Expression::Error(x) => visitor.visit_error(x),
+    }
+}
+
+pub fn walk_integer_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut Integer) {}
+
+pub fn walk_float_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut Float) {}
+
+pub fn walk_identifier_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut Identifier) {}
+
+pub fn walk_literal_string_mut<V: VisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _node: &mut LiteralString,
+) {
+}
+
+pub fn walk_boolean_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut Boolean) {}
+
+pub fn walk_null_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut Null) {}
+
+pub fn walk_path_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Path) {
+    for child in node.parts.iter_mut() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_search_nix_path_mut<V: VisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _node: &mut SearchNixPath,
+) {
+}
+
+pub fn walk_uri_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut Uri) {}
+
+pub fn walk_unary_operation_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    node: &mut UnaryOperation,
+) {
+    visitor.visit_expression(&mut node.expression);
+}
+
+pub fn walk_binary_operation_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    node: &mut BinaryOperation,
+) {
+    visitor.visit_expression(&mut node.left);
+    visitor.visit_expression(&mut node.right);
+}
+
+pub fn walk_list_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut List) {
+    for child in node.elements.iter_mut() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_attr_set_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut AttrSet) {
+    for child in node.bindings.iter_mut() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_if_then_else_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut IfThenElse) {
+    visitor.visit_expression(&mut node.predicate);
+    visitor.visit_expression(&mut node.then);
+    visitor.visit_expression(&mut node.else_);
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Function) {
+    visitor.visit_function_head(&mut node.head);
+    visitor.visit_expression(&mut node.body);
+}
+
+pub fn walk_function_application_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    node: &mut FunctionApplication,
+) {
+    visitor.visit_expression(&mut node.function);
+    for child in node.arguments.iter_mut() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_property_access_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    node: &mut PropertyAccess,
+) {
+    visitor.visit_expression(&mut node.expression);
+    visitor.visit_attr_path(&mut node.attribute_path);
+    if let Some(child) = &mut node.default {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_has_attribute_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut HasAttribute) {
+    visitor.visit_expression(&mut node.expression);
+    visitor.visit_attr_path(&mut node.attribute_path);
+}
+
+pub fn walk_nix_string_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut NixString) {
+    for child in node.parts.iter_mut() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_indented_string_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    node: &mut IndentedString,
+) {
+    for child in node.parts.iter_mut() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_part_raw_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut PartRaw) {}
+
+pub fn walk_part_interpolation_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    node: &mut PartInterpolation,
+) {
+    visitor.visit_expression(&mut node.expression);
+}
+
+pub fn walk_binding_inherit_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    node: &mut BindingInherit,
+) {
+    if let Some(child) = &mut node.from_ {
+        visitor.visit_expression(child);
+    }
+    for child in node.attributes.iter_mut() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_binding_key_value_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    node: &mut BindingKeyValue,
+) {
+    visitor.visit_attr_path(&mut node.from_);
+    visitor.visit_expression(&mut node.to);
+}
+
+pub fn walk_with_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut With) {
+    visitor.visit_expression(&mut node.scope);
+    visitor.visit_expression(&mut node.body);
+}
+
+pub fn walk_let_in_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut LetIn) {
+    for child in node.bindings.iter_mut() {
+        visitor.visit_expression(child);
+    }
+    visitor.visit_expression(&mut node.target);
+}
+
+pub fn walk_assert_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Assert) {
+    visitor.visit_expression(&mut node.condition);
+    visitor.visit_expression(&mut node.body);
+}
+
+pub fn walk_throw_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Throw) {
+    visitor.visit_expression(&mut node.message);
+}
+
+pub fn walk_error_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut Error) {}
+
+pub fn walk_attr_path_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut AttrPath) {
+    for child in node.attributes.iter_mut() {
+        visitor.visit_expression(child);
+    }
+}
+
+pub fn walk_function_head_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut FunctionHead) {
+    match node {
+        FunctionHead::FunctionHeadSimple(head) => visitor.visit_identifier(&mut head.identifier),
+        FunctionHead::FunctionHeadDestructured(head) => {
+            let (before, after) = if head.identifier_before {
+                (head.identifier.as_mut(), None)
+            } else {
+                (None, head.identifier.as_mut())
+            };
+            if let Some(identifier) = before {
+                visitor.visit_identifier(identifier);
+            }
+            for argument in head.arguments.iter_mut() {
+                visitor.visit_identifier(&mut argument.identifier);
+                if let Some(default) = &mut argument.default {
+                    visitor.visit_expression(default);
+                }
+            }
+            if let Some(identifier) = after {
+                visitor.visit_identifier(identifier);
+            }
+        }
+    }
+}
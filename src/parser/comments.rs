@@ -0,0 +1,175 @@
+use super::ast::core::{Comment, Position, Span, Trivia};
+use super::ast::{Expression, FunctionHead, FunctionHeadDestructuredArgument};
+use super::cst::{SyntaxNode, SyntaxToken, TokenKind};
+
+/// A comment found in the source, plus whether it sits on a line of its own
+/// (only whitespace before it) rather than at the end of some code.
+pub(crate) struct CollectedComment {
+    comment: Comment,
+    own_line: bool,
+    /// Where the code before the comment ends, such as a `;` or `)`.
+    code_before: usize,
+    /// Where the code after the comment starts.
+    code_after: usize,
+}
+
+/// Picks the comment tokens out of the syntax tree of `input`.
+pub(crate) fn collect(cst: &SyntaxNode, input: &str) -> Vec<CollectedComment> {
+    let tokens = cst.tokens();
+    // Commas only separate formals, and a comment on either side of one
+    // belongs to the formal next to it.
+    let is_code = |token: &SyntaxToken| {
+        !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment) && token.text != ","
+    };
+    let mut comments = Vec::new();
+    let mut code_before = 0;
+    for (index, token) in tokens.iter().enumerate() {
+        if is_code(token) {
+            code_before = token.range().end;
+            continue;
+        }
+        if token.kind != TokenKind::Comment {
+            continue;
+        }
+        let code_after = tokens[index..]
+            .iter()
+            .find(|token| is_code(token))
+            .map_or(input.len(), |token| token.offset);
+        let line_start = input[..token.offset].rfind('\n').map_or(0, |i| i + 1);
+        comments.push(CollectedComment {
+            comment: Comment::new(
+                token.text.clone(),
+                Span::new(position(input, token.offset), position(input, token.range().end)),
+            ),
+            own_line: input[line_start..token.offset].trim().is_empty(),
+            code_before,
+            code_after,
+        });
+    }
+    comments
+}
+
+fn position(input: &str, offset: usize) -> Position {
+    Position::from_pest_position(
+        pest::Position::new(input, offset).expect("token offsets lie on character boundaries"),
+    )
+}
+
+/// Attaches comments to the innermost node whose span encloses them.
+///
+/// Inside that node a comment on its own line leads the next child, while an
+/// end-of-line comment trails the previous one. A comment never moves across
+/// code such as a `;` to get there, so `assert a; # c` leads the body rather
+/// than trailing `a`. When the node has no child on the allowed sides the
+/// comment goes inside an empty list or set, and failing that to the node
+/// itself. The formals of a function head count as its children.
+pub(crate) fn attach(expressions: &mut [Expression], comments: Vec<CollectedComment>) {
+    for comment in comments {
+        // The grammar always yields a root expression, so nothing is left over.
+        let _ = place(expressions.iter_mut().map(Child::Expression).collect(), comment);
+    }
+}
+
+/// Something comments attach to: a node, or a formal of a function head.
+enum Child<'a> {
+    Expression(&'a mut Expression),
+    Formal(&'a mut FunctionHeadDestructuredArgument),
+}
+
+impl<'a> Child<'a> {
+    fn span(&self) -> Span {
+        match self {
+            Child::Expression(x) => x.span(),
+            Child::Formal(x) => x.span.clone(),
+        }
+    }
+
+    fn trivia_mut(self) -> &'a mut Trivia {
+        match self {
+            Child::Expression(x) => x.trivia_mut(),
+            Child::Formal(x) => &mut x.trivia,
+        }
+    }
+}
+
+/// The children of `node`, with the formals of a function in place of their
+/// defaults.
+fn children(node: &mut Expression) -> Vec<Child<'_>> {
+    let Expression::Function(function) = node else {
+        return node.children_mut().into_iter().map(Child::Expression).collect();
+    };
+    let mut children = Vec::new();
+    if let FunctionHead::FunctionHeadDestructured(head) = &mut function.head {
+        children.extend(head.arguments.iter_mut().map(Child::Formal));
+    }
+    children.push(Child::Expression(&mut function.body));
+    children
+}
+
+fn attach_to(child: Child, comment: CollectedComment) {
+    let (comment, empty, trivia) = match child {
+        Child::Expression(node) => {
+            let Some(comment) = place(children(node), comment) else {
+                return;
+            };
+            let empty = match node {
+                Expression::List(x) => x.elements.is_empty(),
+                Expression::AttrSet(x) => x.bindings.is_empty(),
+                _ => false,
+            };
+            (comment, empty, node.trivia_mut())
+        }
+        Child::Formal(formal) => {
+            let default = formal.default.iter_mut().map(Child::Expression).collect();
+            let Some(comment) = place(default, comment) else {
+                return;
+            };
+            (comment, false, &mut formal.trivia)
+        }
+    };
+    if empty {
+        trivia.inner.push(comment.comment);
+    } else if comment.own_line {
+        trivia.leading.push(comment.comment);
+    } else {
+        trivia.trailing.push(comment.comment);
+    }
+}
+
+/// Places `comment` among `children`, handing it back if there are none.
+fn place(mut children: Vec<Child>, comment: CollectedComment) -> Option<CollectedComment> {
+    let start = comment.comment.span.start.offset;
+    let end = comment.comment.span.end.offset;
+
+    if let Some(index) = children.iter().position(|child| {
+        let span = child.span();
+        span.start.offset <= start && end <= span.end.offset
+    }) {
+        attach_to(children.swap_remove(index), comment);
+        return None;
+    }
+
+    let preceding = children.iter().rposition(|child| child.span().end.offset <= start);
+    let following = children.iter().position(|child| child.span().start.offset >= end);
+    // Only whitespace and comments may lie between a comment and its node.
+    let trails = preceding.filter(|&i| children[i].span().end.offset >= comment.code_before);
+    let leads = following.filter(|&i| children[i].span().start.offset <= comment.code_after);
+    let (index, leading) = match (comment.own_line, trails, leads) {
+        (true, _, Some(index)) | (false, None, Some(index)) => (index, true),
+        (_, Some(index), _) => (index, false),
+        // Code on both sides, as in `[ (a b) # c ]`: prefer the previous
+        // child, whose parentheses are rendered inside its comments.
+        _ => match (preceding, following) {
+            (Some(index), _) => (index, false),
+            (None, Some(index)) => (index, true),
+            (None, None) => return Some(comment),
+        },
+    };
+    let trivia = children.swap_remove(index).trivia_mut();
+    if leading {
+        trivia.leading.push(comment.comment);
+    } else {
+        trivia.trailing.push(comment.comment);
+    }
+    None
+}
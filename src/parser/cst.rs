@@ -0,0 +1,254 @@
+use std::fmt;
+use std::ops::Range;
+
+use pest::Parser;
+use pest::iterators::Pair;
+
+use super::nesting;
+use super::parser::{NixParser, Rule, pest_error};
+use crate::diagnostic::Diagnostic;
+
+/// What a [`SyntaxToken`] holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// Spaces, tabs and newlines.
+    Whitespace,
+    /// A `# line` or `/* block */` comment, delimiters included.
+    Comment,
+    /// Punctuation and keywords the grammar matches without a rule of their
+    /// own, like `{`, `=`, `;` or `let`.
+    Symbol,
+    /// The text of a grammar rule with no sub-rules, like an identifier, an
+    /// operator or a piece of string content.
+    Text,
+}
+
+#[derive(Clone, Debug)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub text: String,
+    /// Byte offset of the token in the source.
+    pub offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.text.len()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// A node of the lossless syntax tree. Every byte of the source belongs to
+/// exactly one token, so printing the tree gives back the source unchanged.
+#[derive(Clone, Debug)]
+pub struct SyntaxNode {
+    pub rule: Rule,
+    /// Byte range of the node in the source.
+    pub range: Range<usize>,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub(crate) fn from_pair(pair: Pair<Rule>, input: &str) -> Self {
+        let rule = pair.as_rule();
+        let range = pair.as_span().start()..pair.as_span().end();
+        let inner: Vec<Pair<Rule>> = pair
+            .into_inner()
+            .filter(|p| p.as_rule() != Rule::EOI)
+            .collect();
+
+        let mut children = Vec::new();
+        if inner.is_empty() && !is_container(rule) {
+            if !range.is_empty() {
+                children.push(SyntaxElement::Token(SyntaxToken {
+                    kind: TokenKind::Text,
+                    text: input[range.clone()].to_string(),
+                    offset: range.start,
+                }));
+            }
+        } else {
+            let mut cursor = range.start;
+            for child in inner {
+                tokenize(input, cursor..child.as_span().start(), &mut children);
+                cursor = child.as_span().end();
+                children.push(SyntaxElement::Node(SyntaxNode::from_pair(child, input)));
+            }
+            tokenize(input, cursor..range.end, &mut children);
+        }
+        Self { rule, range, children }
+    }
+
+    /// The source text covered by this node.
+    pub fn text(&self) -> String {
+        self.to_string()
+    }
+
+    /// All tokens below this node, in source order.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a SyntaxToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    /// The child nodes, skipping tokens.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// The innermost node whose range covers `range`.
+    pub fn covering_node(&self, range: Range<usize>) -> &SyntaxNode {
+        self.child_nodes()
+            .find(|node| node.range.start <= range.start && range.end <= node.range.end)
+            .map_or(self, |node| node.covering_node(range))
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens() {
+            f.write_str(&token.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rules that may match without producing sub-rules while still holding
+/// whitespace or comments, e.g. `{ }` or `[ /* empty */ ]`.
+fn is_container(rule: Rule) -> bool {
+    matches!(
+        rule,
+        Rule::file
+            | Rule::attrset
+            | Rule::list
+            | Rule::binding_inherit
+            | Rule::function_head_destructured
+    )
+}
+
+/// Splits text the grammar skipped over, or matched without a rule, into
+/// whitespace, comment and symbol tokens.
+fn tokenize(input: &str, range: Range<usize>, tokens: &mut Vec<SyntaxElement>) {
+    let mut offset = range.start;
+    while offset < range.end {
+        let rest = &input[offset..range.end];
+        let first = rest.chars().next().unwrap();
+        let (kind, length) = if first.is_whitespace() {
+            let length = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+            (TokenKind::Whitespace, length)
+        } else if first == '#' {
+            let length = rest.find(['\r', '\n']).unwrap_or(rest.len());
+            (TokenKind::Comment, length)
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            (TokenKind::Comment, body.find("*/").map_or(rest.len(), |i| i + 4))
+        } else if first.is_ascii_alphabetic() {
+            let length = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '\'' || c == '-'))
+                .unwrap_or(rest.len());
+            (TokenKind::Symbol, length)
+        } else if let Some(symbol) = ["${", "''", "..."].iter().find(|s| rest.starts_with(**s)) {
+            (TokenKind::Symbol, symbol.len())
+        } else {
+            (TokenKind::Symbol, first.len_utf8())
+        };
+        tokens.push(SyntaxElement::Token(SyntaxToken {
+            kind,
+            text: rest[..length].to_string(),
+            offset,
+        }));
+        offset += length;
+    }
+}
+
+/// Parses `input` into a lossless syntax tree rooted at `Rule::file`. Broken
+/// regions show up as the grammar's recovery rules, e.g. `Rule::missing`.
+pub fn parse_cst(input: &str) -> Result<SyntaxNode, Box<Diagnostic>> {
+    nesting::check(input)?;
+    let file = NixParser::parse(Rule::file, input)
+        .map_err(pest_error)?
+        .next()
+        .unwrap();
+    Ok(SyntaxNode::from_pair(file, input))
+}
+
+/// Replaces `range` of some source text with `replacement`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, replacement: String) -> Self {
+        Self { range, replacement }
+    }
+}
+
+/// Why a set of [`TextEdit`]s could not be applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditError {
+    /// Two edits touch the same text.
+    Overlapping(Range<usize>, Range<usize>),
+    /// An edit's range is reversed, runs past the end of the source or splits
+    /// a character.
+    InvalidRange(Range<usize>),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::Overlapping(a, b) => {
+                write!(f, "Overlapping text edits: {:?} and {:?}", a, b)
+            }
+            EditError::InvalidRange(range) => write!(f, "Invalid text edit range: {:?}", range),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// Applies non-overlapping edits to `source`, leaving everything outside
+/// their ranges untouched.
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> Result<String, EditError> {
+    let mut edits: Vec<&TextEdit> = edits.iter().collect();
+    edits.sort_by_key(|edit| (edit.range.start, edit.range.end));
+
+    let mut result = String::with_capacity(source.len());
+    let mut previous: Option<&TextEdit> = None;
+    for edit in edits {
+        let range = &edit.range;
+        if range.start > range.end
+            || !source.is_char_boundary(range.start)
+            || !source.is_char_boundary(range.end)
+        {
+            return Err(EditError::InvalidRange(range.clone()));
+        }
+        let cursor = previous.map_or(0, |previous| previous.range.end);
+        if let Some(previous) = previous
+            && range.start < cursor
+        {
+            return Err(EditError::Overlapping(previous.range.clone(), range.clone()));
+        }
+        result.push_str(&source[cursor..range.start]);
+        result.push_str(&edit.replacement);
+        previous = Some(edit);
+    }
+    result.push_str(&source[previous.map_or(0, |previous| previous.range.end)..]);
+    Ok(result)
+}
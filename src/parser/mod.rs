@@ -17,5 +17,8 @@
 // -----------------------------------------------------------------------------------------|
 
 pub mod ast;
+mod comments;
+pub mod cst;
+mod nesting;
+#[allow(clippy::module_inception)]
 pub mod parser;
-mod utils;
\ No newline at end of file
@@ -0,0 +1,325 @@
+//! A quick scan of how deeply the constructs of a file nest, run before it is
+//! parsed.
+//!
+//! Both the grammar and the code building the tree from it recurse once per
+//! level of nesting, so deep enough input would overflow the stack and abort
+//! the process. The scan is a rough lexer that keeps a stack of the
+//! constructs left open at each point. It errs on the side of counting too
+//! many, never too few, so input it accepts is safe to parse.
+//!
+//! Each construct is charged what it costs further down: besides parsing,
+//! cloning, comparing and rendering the tree recurse once per node. The
+//! costs below are in quarters of a bracket, measured on a 2MB stack in a
+//! debug build.
+
+use super::ast::core::Span;
+use crate::diagnostic::{Diagnostic, codes};
+
+/// How many brackets may nest. Other constructs take their own share of a
+/// level: function bodies, branches and prefix operators three quarters, the
+/// operands of an infix chain a quarter each.
+pub const MAX_NESTING: usize = 80;
+
+/// The cost of a bracket, the unit [`MAX_NESTING`] counts in.
+const LEVEL_COST: usize = 4;
+
+/// A construct left open at some point of the input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Frame {
+    /// `(`, `[` or `{`, up to the matching bracket.
+    Bracket,
+    /// `${`, up to its `}`.
+    Interpolation,
+    /// The content of a `"` string.
+    String,
+    /// The content of a `''` string.
+    IndentedString,
+    /// `name =`, up to its `;`.
+    Binding,
+    /// The scope of `with` or the condition of `assert`, up to their `;`.
+    Scope,
+    /// The bindings of `let`, up to `in`.
+    Let,
+    /// The condition of `if`, up to `then`.
+    If,
+    /// The `then` branch, up to `else`.
+    Then,
+    /// Anything that runs to the end of whatever encloses it: a function
+    /// body, the operand of a prefix operator, the body of `let`, `with` and
+    /// `assert`, and the `else` branch.
+    Open,
+    /// The right operand of an infix operator or `?`, which also runs to the
+    /// end of whatever encloses it. The tree nests a chain of them one node
+    /// per operand, but the grammar reads it as one flat sequence.
+    Operand,
+}
+
+impl Frame {
+    fn is_open(self) -> bool {
+        matches!(self, Frame::Open | Frame::Operand)
+    }
+
+    fn cost(self) -> usize {
+        match self {
+            Frame::Bracket | Frame::Interpolation => LEVEL_COST,
+            Frame::String | Frame::IndentedString | Frame::Binding => 2,
+            Frame::Scope | Frame::Let | Frame::If | Frame::Then | Frame::Open => 3,
+            Frame::Operand => 1,
+        }
+    }
+}
+
+/// The state of the scan at some point of the input.
+#[derive(Default)]
+struct Scan {
+    frames: Vec<Frame>,
+    /// Whether the last token ends an operand, which makes a `-` after it
+    /// infix rather than prefix.
+    after_operand: bool,
+}
+
+impl Scan {
+    fn push(&mut self, frame: Frame) {
+        self.frames.push(frame);
+        self.after_operand = false;
+    }
+
+    fn cost(&self) -> usize {
+        self.frames.iter().map(|frame| frame.cost()).sum()
+    }
+}
+
+/// Fails with a diagnostic if constructs in `input` nest more than
+/// [`MAX_NESTING`] levels deep.
+pub(crate) fn check(input: &str) -> Result<(), Box<Diagnostic>> {
+    let bytes = input.as_bytes();
+    let mut scan = Scan::default();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        i = match scan.frames.last() {
+            Some(Frame::String) => string(bytes, i, &mut scan),
+            Some(Frame::IndentedString) => indented_string(bytes, i, &mut scan),
+            _ => code(bytes, i, &mut scan),
+        };
+        if scan.cost() > MAX_NESTING * LEVEL_COST {
+            return Err(too_deep(input, start).into());
+        }
+    }
+    Ok(())
+}
+
+fn too_deep(input: &str, offset: usize) -> Diagnostic {
+    let end = offset + input[offset..].chars().next().map_or(0, char::len_utf8);
+    let span = Span::from_pest_span(pest::Span::new(input, offset, end).unwrap());
+    Diagnostic::error(
+        codes::TOO_DEEPLY_NESTED,
+        format!("code nests more than {} levels deep", MAX_NESTING),
+        span,
+    )
+    .with_label("too deeply nested".to_string())
+    .with_help("move inner parts into `let` bindings".to_string())
+}
+
+/// Scans one token of `"` string content at `i`, returning where the next
+/// one starts.
+fn string(bytes: &[u8], i: usize, scan: &mut Scan) -> usize {
+    match &bytes[i..] {
+        [b'\\', ..] | [b'$', b'$', ..] => i + 2,
+        [b'$', b'{', ..] => {
+            scan.push(Frame::Interpolation);
+            i + 2
+        }
+        [b'"', ..] => {
+            scan.frames.pop();
+            scan.after_operand = true;
+            i + 1
+        }
+        _ => i + 1,
+    }
+}
+
+fn indented_string(bytes: &[u8], i: usize, scan: &mut Scan) -> usize {
+    match &bytes[i..] {
+        [b'\'', b'\'', b'\\', ..] => i + 4,
+        [b'\'', b'\'', b'\'' | b'$', ..] | [b'$', b'$', ..] => i + 3,
+        [b'$', b'{', ..] => {
+            scan.push(Frame::Interpolation);
+            i + 2
+        }
+        [b'\'', b'\'', ..] => {
+            scan.frames.pop();
+            scan.after_operand = true;
+            i + 2
+        }
+        _ => i + 1,
+    }
+    .min(bytes.len())
+}
+
+/// Scans one token of code at `i`, returning where the next one starts.
+fn code(bytes: &[u8], i: usize, scan: &mut Scan) -> usize {
+    let rest = &bytes[i..];
+    let push = |scan: &mut Scan, frame, length| {
+        scan.push(frame);
+        i + length
+    };
+    let operand = |scan: &mut Scan, length| {
+        scan.after_operand = true;
+        i + length
+    };
+    match rest {
+        [b'#', ..] => find(bytes, i, b"\n"),
+        [b'/', b'*', ..] => find(bytes, i + 2, b"*/") + 2,
+        [b'"', ..] => push(scan, Frame::String, 1),
+        [b'\'', b'\'', ..] => push(scan, Frame::IndentedString, 2),
+        [b'$', b'{', ..] => push(scan, Frame::Interpolation, 2),
+        [b'(' | b'[' | b'{', ..] => push(scan, Frame::Bracket, 1),
+        [b')' | b']' | b'}', ..] => {
+            while let Some(frame) = scan.frames.pop() {
+                if matches!(frame, Frame::Bracket | Frame::Interpolation) {
+                    break;
+                }
+            }
+            operand(scan, 1)
+        }
+        _ if path_length(rest) > 0 => operand(scan, path_length(rest)),
+        [b'<', ..] if search_path_length(rest) > 0 => operand(scan, search_path_length(rest)),
+        [c, ..] if c.is_ascii_alphabetic() || *c == b'_' => word(bytes, i, scan),
+        [c, ..] if c.is_ascii_digit() => operand(scan, number(bytes, i) - i),
+        [b'.', b'.', b'.', ..] => i + 3,
+        [b'-', b'>', ..]
+        | [b'/', b'/', ..]
+        | [b'+', b'+', ..]
+        | [b'=' | b'!' | b'<' | b'>', b'=', ..]
+        | [b'&', b'&', ..]
+        | [b'|', b'|', ..] => push(scan, Frame::Operand, 2),
+        [b'+' | b'*' | b'/' | b'<' | b'>' | b'?', ..] => push(scan, Frame::Operand, 1),
+        [b'-', ..] if scan.after_operand => push(scan, Frame::Operand, 1),
+        // A prefix operator recurses like any other level.
+        [b'-' | b'!' | b':', ..] => push(scan, Frame::Open, 1),
+        [b'=', ..] => push(scan, Frame::Binding, 1),
+        [b';', ..] => {
+            if end(&mut scan.frames, &[Frame::Binding, Frame::Scope]) == Some(Frame::Scope) {
+                scan.frames.push(Frame::Open);
+            }
+            scan.after_operand = false;
+            i + 1
+        }
+        // Commas only separate the formals of a function head, ending the
+        // default before them.
+        [b',', ..] => {
+            while scan.frames.last().is_some_and(|frame| frame.is_open()) {
+                scan.frames.pop();
+            }
+            scan.after_operand = false;
+            i + 1
+        }
+        _ => i + 1,
+    }
+}
+
+/// Pops open frames and then the innermost frame in `targets`, returning it.
+/// Pops nothing if another frame comes first, as the token then belongs to
+/// malformed code that the parser reports.
+fn end(frames: &mut Vec<Frame>, targets: &[Frame]) -> Option<Frame> {
+    let open = frames.iter().rev().take_while(|frame| frame.is_open()).count();
+    let target = frames.len().checked_sub(open + 1)?;
+    let frame = frames[target];
+    if !targets.contains(&frame) {
+        return None;
+    }
+    frames.truncate(target);
+    Some(frame)
+}
+
+/// An identifier, keyword or URI starting at `i`.
+fn word(bytes: &[u8], i: usize, scan: &mut Scan) -> usize {
+    let length = bytes[i..]
+        .iter()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'\'' | b'-'))
+        .count();
+    let stop = i + length;
+    if let [b':', c, ..] = &bytes[stop..]
+        && is_uri_char(*c)
+    {
+        scan.after_operand = true;
+        return stop + 1 + bytes[stop + 1..].iter().take_while(|c| is_uri_char(**c)).count();
+    }
+    let frames = &mut scan.frames;
+    let replace = |frames: &mut Vec<Frame>, target, frame| {
+        if end(frames, &[target]).is_some() {
+            frames.push(frame);
+        }
+    };
+    match &bytes[i..stop] {
+        b"let" => frames.push(Frame::Let),
+        b"in" => replace(frames, Frame::Let, Frame::Open),
+        b"if" => frames.push(Frame::If),
+        b"then" => replace(frames, Frame::If, Frame::Then),
+        b"else" => replace(frames, Frame::Then, Frame::Open),
+        b"with" | b"assert" => frames.push(Frame::Scope),
+        // The default after `or` is a single operand, nesting no deeper.
+        b"or" | b"rec" | b"inherit" => {}
+        _ => {
+            scan.after_operand = true;
+            return stop;
+        }
+    }
+    scan.after_operand = false;
+    stop
+}
+
+/// A number starting at `i`, exponent included so that its sign is not
+/// taken for an operator.
+fn number(bytes: &[u8], i: usize) -> usize {
+    let mut end = i + bytes[i..].iter().take_while(|c| c.is_ascii_digit() || **c == b'.').count();
+    if let [b'e' | b'E', rest @ ..] = &bytes[end..] {
+        let sign = usize::from(matches!(rest, [b'+' | b'-', ..]));
+        let digits = rest[sign..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if digits > 0 {
+            end += 1 + sign + digits;
+        }
+    }
+    end
+}
+
+/// The length of the path at the start of `rest`, or 0 if there is none. An
+/// interpolation ends it early; what follows is scanned as code.
+fn path_length(rest: &[u8]) -> usize {
+    let start = match rest {
+        [b'~', b'/', ..] => 1,
+        _ => rest.iter().take_while(|c| is_path_char(**c)).count(),
+    };
+    match &rest[start..] {
+        [b'/', c, ..] if is_path_char(*c) => {}
+        [b'/', b'$', b'{', ..] => return start + 1,
+        _ => return 0,
+    }
+    start + rest[start..].iter().take_while(|c| is_path_char(**c) || **c == b'/').count()
+}
+
+/// The length of the `<search/path>` at the start of `rest`, or 0.
+fn search_path_length(rest: &[u8]) -> usize {
+    let length = rest[1..].iter().take_while(|c| is_path_char(**c) || **c == b'/').count();
+    match rest.get(1 + length) {
+        Some(b'>') if length > 0 => length + 2,
+        _ => 0,
+    }
+}
+
+fn is_path_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'.' | b'_' | b'-' | b'+')
+}
+
+fn is_uri_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"%/?:@&=+$,-_.!~*'".contains(&c)
+}
+
+/// The offset of the next `needle` at or after `i`, or the end of `bytes`.
+fn find(bytes: &[u8], i: usize, needle: &[u8]) -> usize {
+    bytes[i.min(bytes.len())..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map_or(bytes.len(), |position| i + position)
+}
@@ -1,67 +1,632 @@
+use std::sync::LazyLock;
+
 use pest::Parser;
+use pest::error::{InputLocation, LineColLocation};
+use pest::iterators::Pair;
+use pest::pratt_parser::{Assoc, Op, PrattParser};
 use pest_derive::Parser;
 
-use super::ast::errors::SyntaxError;
+use crate::diagnostic::{Diagnostic, Label, codes};
+use super::comments;
+use super::cst::SyntaxNode;
+use super::nesting;
 
-use super::ast::BinaryOperation;
+use super::ast::{BinaryOperation, FunctionApplication, UnaryOperation};
+use super::ast::{AttrPath, AttrSet, BindingInherit, BindingKeyValue};
+use super::ast::{HasAttribute, PropertyAccess};
+use super::ast::{Assert, IfThenElse, LetIn, List, With};
+use super::ast::{
+    Function, FunctionHead, FunctionHeadDestructured, FunctionHeadDestructuredArgument,
+    FunctionHeadSimple,
+};
 
-use super::ast::Expression;
-use super::ast::core::{Boolean, Integer, Identifier, Operator};
+use super::ast::{Expression, IndentedString, NixString, PartInterpolation, PartRaw};
+use super::ast::core::{
+    Associativity, Boolean, Error, Float, Integer, Identifier, Null, Operator, Position, Span,
+};
+use super::ast::core::{Path, PathKind, SearchNixPath, Uri};
 
 #[derive(Parser)]
 #[grammar = "src/parser/nix.pest"]
 pub struct NixParser;
 
-pub fn parse(input: &str) -> Result<Vec<Expression>, SyntaxError> {
-    let pairs = NixParser::parse(Rule::expr, input)
-        .map_err(|e| SyntaxError {message: e.to_string()})?;
-    
+/// Operator table for `Rule::binary`, from loosest to tightest binding.
+static PRATT_PARSER: LazyLock<PrattParser<Rule>> = LazyLock::new(|| {
+    PrattParser::new()
+        .op(Op::infix(Rule::op_impl, Assoc::Right))
+        .op(Op::infix(Rule::op_or, Assoc::Left))
+        .op(Op::infix(Rule::op_and, Assoc::Left))
+        .op(Op::infix(Rule::op_eq, Assoc::Left) | Op::infix(Rule::op_neq, Assoc::Left))
+        .op(Op::infix(Rule::op_lt, Assoc::Left)
+            | Op::infix(Rule::op_lte, Assoc::Left)
+            | Op::infix(Rule::op_gt, Assoc::Left)
+            | Op::infix(Rule::op_gte, Assoc::Left))
+        .op(Op::infix(Rule::op_update, Assoc::Right))
+        .op(Op::prefix(Rule::op_not))
+        .op(Op::infix(Rule::op_add, Assoc::Left) | Op::infix(Rule::op_sub, Assoc::Left))
+        .op(Op::infix(Rule::op_mul, Assoc::Left) | Op::infix(Rule::op_div, Assoc::Left))
+        .op(Op::infix(Rule::op_concat, Assoc::Right))
+        .op(Op::postfix(Rule::has_attribute))
+        .op(Op::prefix(Rule::op_negate))
+});
+
+/// Output of [`parse_recovering`]: the tree, with `Expression::Error` nodes
+/// standing in for whatever could not be parsed, and one diagnostic per such
+/// node.
+#[derive(Debug)]
+pub struct Parsed {
+    pub expressions: Vec<Expression>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Parses `input`, failing on the first syntax error.
+pub fn parse(input: &str) -> Result<Vec<Expression>, Box<Diagnostic>> {
+    let parsed = parse_recovering(input);
+    match parsed.diagnostics.into_iter().next() {
+        Some(error) => Err(Box::new(error)),
+        None => Ok(parsed.expressions),
+    }
+}
+
+/// Parses `input` without giving up on syntax errors, for editors that need a
+/// tree while code is still being typed.
+pub fn parse_recovering(input: &str) -> Parsed {
+    // Code nested too deeply to parse without overflowing the stack is kept
+    // as one error, as is input the grammar rejects. The recovery rules
+    // accept any input, so the latter is not expected to happen.
+    let file = nesting::check(input)
+        .and_then(|()| NixParser::parse(Rule::file, input).map_err(|e| pest_error(e).into()));
+    let file = match file {
+        Ok(mut pairs) => pairs.next().unwrap(),
+        Err(diagnostic) => {
+            let error = Error::new(diagnostic);
+            return Parsed {
+                diagnostics: vec![(*error.diagnostic).clone()],
+                expressions: vec![Expression::Error(error)],
+            };
+        }
+    };
+    let cst = SyntaxNode::from_pair(file.clone(), input);
+    let comments = comments::collect(&cst, input);
+
     let mut expressions = Vec::new();
-    for pair in pairs {
-        let expr = parse_expr(pair)?;
-        expressions.push(expr);
+    for pair in file.into_inner() {
+        if pair.as_rule() == Rule::EOI {
+            continue;
+        }
+        expressions.push(parse_or_error(pair));
+    }
+    comments::attach(&mut expressions, comments);
+
+    let mut diagnostics = Vec::new();
+    for expression in &expressions {
+        collect_diagnostics(expression, &mut diagnostics);
+    }
+    Parsed { expressions, diagnostics }
+}
+
+fn collect_diagnostics(expression: &Expression, diagnostics: &mut Vec<Diagnostic>) {
+    if let Expression::Error(error) = expression {
+        diagnostics.push((*error.diagnostic).clone());
     }
-    Ok(expressions)
+    for child in expression.children() {
+        collect_diagnostics(child, diagnostics);
+    }
+}
+
+/// Turns a pest error into a diagnostic at the position pest reports.
+pub(crate) fn pest_error(error: pest::error::Error<Rule>) -> Diagnostic {
+    let ((start_line, start_column), (end_line, end_column)) = match error.line_col {
+        LineColLocation::Pos(position) => (position, position),
+        LineColLocation::Span(start, end) => (start, end),
+    };
+    let (start_offset, end_offset) = match error.location {
+        InputLocation::Pos(offset) => (offset, offset),
+        InputLocation::Span(range) => range,
+    };
+    Diagnostic::error(
+        codes::UNEXPECTED_INPUT,
+        error.variant.message().to_string(),
+        Span::new(
+            Position::new(start_line as i64, start_column as i64, start_offset),
+            Position::new(end_line as i64, end_column as i64, end_offset),
+        ),
+    )
+}
+
+/// Parses `pair`, turning a failure into an error node so that one bad
+/// binding or list element does not take its siblings down with it.
+fn parse_or_error(pair: Pair<Rule>) -> Expression {
+    parse_expr(pair).unwrap_or_else(|e| Expression::Error(Error::new(e)))
 }
 
-pub fn parse_expr(pair: pest::iterators::Pair<Rule>) -> Result<Expression, SyntaxError> {
+/// Builds the error node for one of the grammar's recovery rules.
+fn parse_recovery(pair: Pair<Rule>) -> Expression {
+    let span = Span::from_pest_span(pair.as_span());
+    let text = pair.as_str();
+    let diagnostic = match pair.as_rule() {
+        Rule::missing => Diagnostic::error(
+            codes::EXPECTED_EXPRESSION,
+            "expected an expression".to_string(),
+            span.clone(),
+        )
+        .with_label("expected an expression here".to_string()),
+        Rule::unclosed => Diagnostic::error(
+            codes::UNCLOSED_DELIMITER,
+            "unexpected end of input".to_string(),
+            span.clone(),
+        )
+        .with_label("expected a closing bracket".to_string()),
+        Rule::unexpected => {
+            // Only point at the first word; the rest of the file follows it.
+            let word = text.split_whitespace().next().unwrap_or(text);
+            let start = pair.as_span().start();
+            let word_span = pest::Span::new(pair.get_input(), start, start + word.len()).unwrap();
+            Diagnostic::error(
+                codes::UNEXPECTED_INPUT,
+                format!("unexpected `{}`", word),
+                Span::from_pest_span(word_span),
+            )
+            .with_label("expected the end of the file".to_string())
+        }
+        Rule::binding_error => Diagnostic::error(
+            codes::INVALID_BINDING,
+            "expected a binding".to_string(),
+            span.clone(),
+        )
+        .with_label("not a binding".to_string())
+        .with_help("bindings look like `name = value;` or `inherit name;`".to_string()),
+        Rule::list_error => Diagnostic::error(
+            codes::INVALID_LIST_ELEMENT,
+            format!("unexpected `{}` in list", text),
+            span.clone(),
+        )
+        .with_help(
+            "list elements are separated by whitespace; \
+             wrap operations and function calls in parentheses"
+                .to_string(),
+        ),
+        _ => unreachable!(),
+    };
+    Expression::Error(Error::new(Box::new(diagnostic)))
+}
+
+/// Points the error for a missing `}` or `]` back at the collection that was
+/// left open.
+fn label_unclosed(elements: &mut [Expression], opened: &Span) {
+    if let Some(Expression::Error(error)) = elements.last_mut()
+        && error.diagnostic.code == codes::UNCLOSED_DELIMITER
+    {
+        let start = opened.start.clone();
+        let end = Position::new(start.line, start.column + 1, start.offset + 1);
+        let secondary = Label::new(Span::new(start, end), "unclosed here".to_string());
+        error.diagnostic.secondary.push(secondary);
+    }
+}
+
+pub fn parse_expr(pair: pest::iterators::Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    // Each rule is built by a function of its own. One function holding the
+    // locals of every rule would need a frame so large that moderately nested
+    // code overflows the stack of a debug build.
+    match pair.as_rule() {
+        Rule::integer
+        | Rule::float
+        | Rule::boolean
+        | Rule::null
+        | Rule::identifier
+        | Rule::search_path
+        | Rule::uri
+        | Rule::string_text
+        | Rule::indented_string_text
+        | Rule::path_start
+        | Rule::path_text => parse_literal(pair),
+        Rule::string => parse_string(pair),
+        Rule::indented_string => parse_indented_string(pair),
+        Rule::path => parse_path(pair),
+        Rule::interpolation => parse_interpolation(pair),
+        Rule::binary => parse_binary(pair),
+        Rule::function => parse_function(pair),
+        Rule::let_in => parse_let_in(pair),
+        Rule::with => parse_with(pair),
+        Rule::assert => parse_assert(pair),
+        Rule::if_then_else => parse_if_then_else(pair),
+        Rule::list => parse_list(pair),
+        Rule::attrset => parse_attrset(pair),
+        Rule::binding_key_value => parse_binding_key_value(pair),
+        Rule::binding_inherit => parse_binding_inherit(pair),
+        Rule::application => parse_application(pair),
+        Rule::select => parse_select(pair),
+        Rule::primary | Rule::expr | Rule::parenthesized => {
+            parse_expr(pair.into_inner().next().unwrap())
+        }
+        Rule::missing
+        | Rule::unclosed
+        | Rule::unexpected
+        | Rule::binding_error
+        | Rule::list_error => Ok(parse_recovery(pair)),
+        _ => unreachable!(),
+    }
+}
+
+/// Builds the nodes that have no sub-expressions.
+fn parse_literal(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
     match pair.as_rule() {
         Rule::integer => {
-            Ok(Expression::Integer(Integer::new(pair.as_str().parse().unwrap())))
+            let span = Span::from_pest_span(pair.as_span());
+            Ok(Expression::Integer(Integer::from_lexeme(pair.as_str(), span)?))
+        }
+        Rule::float => {
+            let span = Span::from_pest_span(pair.as_span());
+            Ok(Expression::Float(Float::from_lexeme(pair.as_str(), span)?))
         }
         Rule::boolean => {
-            Ok(Expression::Boolean(Boolean::new(pair.as_str() == "true")))
+            Ok(Expression::Boolean(Boolean::new_span(
+                pair.as_str() == "true",
+                Span::from_pest_span(pair.as_span()),
+            )))
+        }
+        Rule::null => {
+            Ok(Expression::Null(Null::new_span(Span::from_pest_span(pair.as_span()))))
         }
         Rule::identifier => {
-            Ok(Expression::Identifier(Identifier::new(pair.as_str().to_string())))
-        }
-        Rule::binary => {
-            let mut inner = pair.into_inner();
-            let first = parse_expr(inner.next().unwrap());
-            let mut expr = first;
-            while let Some(op_pair) = inner.next() {
-                let op = parse_operator(op_pair);
-                let right = parse_expr(inner.next().unwrap());
-                let operation = BinaryOperation::new(
-                    expr?,
-                    op,
-                    right?,
-                )?;
-                expr = Ok(Expression::BinaryOperation(operation));
-            }
-            expr
+            Ok(Expression::Identifier(Identifier::parse(pair)))
         }
-        Rule::primary | Rule::expr => {
-            parse_expr(pair.into_inner().next().unwrap())
+        Rule::search_path => {
+            let text = pair.as_str();
+            Ok(Expression::SearchNixPath(SearchNixPath::new_span(
+                text[1..text.len() - 1].to_string(),
+                Span::from_pest_span(pair.as_span()),
+            )))
+        }
+        Rule::uri => {
+            Ok(Expression::Uri(Uri::new_span(
+                pair.as_str().to_string(),
+                Span::from_pest_span(pair.as_span()),
+            )))
+        }
+        Rule::string_text | Rule::indented_string_text | Rule::path_start | Rule::path_text => {
+            Ok(Expression::PartRaw(PartRaw::new_span(
+                pair.as_str().to_string(),
+                Span::from_pest_span(pair.as_span()),
+            )))
         }
         _ => unreachable!(),
     }
 }
 
-fn parse_operator(pair: pest::iterators::Pair<Rule>) -> Operator {
+fn parse_string(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let parts = pair
+        .into_inner()
+        .map(parse_expr)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Expression::NixString(NixString::new_span(parts, span)))
+}
+
+fn parse_indented_string(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let parts = pair
+        .into_inner()
+        .map(parse_expr)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Expression::IndentedString(IndentedString::new_span(parts, span)))
+}
+
+fn parse_path(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let kind = match pair.as_str().chars().next() {
+        Some('/') => PathKind::Absolute,
+        Some('~') => PathKind::Home,
+        _ => PathKind::Relative,
+    };
+    let parts = pair
+        .into_inner()
+        .map(parse_expr)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Expression::Path(Path::new_span(kind, parts, span)))
+}
+
+fn parse_interpolation(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let expression = parse_expr(pair.into_inner().next().unwrap())?;
+    Ok(Expression::PartInterpolation(PartInterpolation::new_span(expression, span)))
+}
+
+fn parse_binary(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    // Most operands stand alone. They skip the Pratt parser, whose frames are
+    // the largest on the way down through nested brackets.
+    let mut inner = pair.clone().into_inner();
+    if let (Some(operand), None) = (inner.next(), inner.next()) {
+        return parse_expr(operand);
+    }
+    check_associativity(&pair)?;
+    PRATT_PARSER
+        .map_primary(parse_expr)
+        .map_prefix(|op, operand| {
+            let operand = operand?;
+            let start = Span::from_pest_span(op.as_span()).start;
+            let span = Span::new(start, operand.span().end);
+            let operation = UnaryOperation::new_span(parse_operator(op)?, operand, span)?;
+            Ok(Expression::UnaryOperation(operation))
+        })
+        .map_postfix(|expression, op| {
+            let expression = expression?;
+            let end = span_of(&op).end;
+            let span = Span::new(expression.span().start, end);
+            let attribute_path = parse_attrpath(op.into_inner().next().unwrap())?;
+            Ok(Expression::HasAttribute(HasAttribute::new_span(expression, attribute_path, span)))
+        })
+        .map_infix(|left, op, right| {
+            let (left, right) = (left?, right?);
+            let span = Span::new(left.span().start, right.span().end);
+            let operation = BinaryOperation::new_span(left, parse_operator(op)?, right, span)?;
+            Ok(Expression::BinaryOperation(operation))
+        })
+        .parse(pair.into_inner())
+}
+
+fn parse_function(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut inner = pair.into_inner();
+    let head = parse_function_head(inner.next().unwrap())?;
+    let body = parse_expr(inner.next().unwrap())?;
+    Ok(Expression::Function(Function::new_span(head, body, span)))
+}
+
+fn parse_let_in(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut bindings: Vec<Expression> = pair.into_inner().map(parse_or_error).collect();
+    let target = bindings.pop().unwrap();
+    Ok(Expression::LetIn(LetIn::new_span(bindings, target, span)))
+}
+
+fn parse_with(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut inner = pair.into_inner();
+    let scope = parse_expr(inner.next().unwrap())?;
+    let body = parse_expr(inner.next().unwrap())?;
+    Ok(Expression::With(With::new_span(scope, body, span)))
+}
+
+fn parse_assert(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut inner = pair.into_inner();
+    let condition = parse_expr(inner.next().unwrap())?;
+    let body = parse_expr(inner.next().unwrap())?;
+    Ok(Expression::Assert(Assert::new_span(condition, body, span)))
+}
+
+fn parse_if_then_else(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut inner = pair.into_inner();
+    let predicate = parse_expr(inner.next().unwrap())?;
+    let then = parse_expr(inner.next().unwrap())?;
+    let else_ = parse_expr(inner.next().unwrap())?;
+    Ok(Expression::IfThenElse(IfThenElse::new_span(predicate, then, else_, span)))
+}
+
+fn parse_list(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut elements: Vec<Expression> = pair.into_inner().map(parse_or_error).collect();
+    label_unclosed(&mut elements, &span);
+    Ok(Expression::List(List::new_span(elements, span)))
+}
+
+fn parse_attrset(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut inner = pair.into_inner().peekable();
+    let recursive = inner.next_if(|p| p.as_rule() == Rule::recursive).is_some();
+    let mut bindings: Vec<Expression> = inner.map(parse_or_error).collect();
+    label_unclosed(&mut bindings, &span);
+    Ok(Expression::AttrSet(AttrSet::new_span(recursive, bindings, span)))
+}
+
+fn parse_binding_key_value(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut inner = pair.into_inner();
+    let from_ = parse_attrpath(inner.next().unwrap())?;
+    let to = parse_expr(inner.next().unwrap())?;
+    Ok(Expression::BindingKeyValue(BindingKeyValue::new_span(from_, to, span)))
+}
+
+fn parse_binding_inherit(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut inner = pair.into_inner().peekable();
+    let from_ = inner
+        .next_if(|p| p.as_rule() == Rule::inherit_from)
+        .map(|p| parse_expr(p.into_inner().next().unwrap()))
+        .transpose()?;
+    let attributes = inner.map(parse_expr).collect::<Result<Vec<_>, _>>()?;
+    Ok(Expression::BindingInherit(BindingInherit::new_span(from_, attributes, span)))
+}
+
+fn parse_application(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut inner = pair.into_inner();
+    let function = parse_expr(inner.next().unwrap())?;
+    let arguments = inner.map(parse_expr).collect::<Result<Vec<_>, _>>()?;
+    if arguments.is_empty() {
+        return Ok(function);
+    }
+    Ok(Expression::FunctionApplication(FunctionApplication::new_span(function, arguments, span)))
+}
+
+fn parse_select(pair: Pair<Rule>) -> Result<Expression, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let mut inner = pair.into_inner();
+    let expression = parse_expr(inner.next().unwrap())?;
+    let Some(attribute_path) = inner.next() else {
+        return Ok(expression);
+    };
+    let attribute_path = parse_attrpath(attribute_path)?;
+    let default = inner.next().map(parse_expr).transpose()?;
+    Ok(Expression::PropertyAccess(PropertyAccess::new_span(expression, attribute_path, default, span)))
+}
+
+fn parse_attrpath(pair: Pair<Rule>) -> Result<AttrPath, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    let attributes = pair.into_inner().map(parse_expr).collect::<Result<Vec<_>, _>>()?;
+    Ok(AttrPath::new_span(attributes, span))
+}
+
+fn parse_function_head(pair: Pair<Rule>) -> Result<FunctionHead, Box<Diagnostic>> {
+    let span = span_of(&pair);
+    match pair.as_rule() {
+        Rule::function_head_simple => {
+            let identifier = Identifier::parse(pair.into_inner().next().unwrap());
+            Ok(FunctionHead::FunctionHeadSimple(FunctionHeadSimple::new_span(identifier, span)))
+        }
+        Rule::function_head_destructured => {
+            let head_start = pair.as_span().start();
+            let mut ellipsis = false;
+            let mut identifier = None;
+            let mut identifier_before = false;
+            let mut arguments = Vec::new();
+            for inner in pair.into_inner() {
+                match inner.as_rule() {
+                    Rule::identifier => {
+                        identifier_before = inner.as_span().start() == head_start;
+                        identifier = Some(Identifier::parse(inner));
+                    }
+                    Rule::formal => {
+                        let formal_span = span_of(&inner);
+                        let mut formal = inner.into_inner();
+                        let name = Identifier::parse(formal.next().unwrap());
+                        let default = formal.next().map(parse_expr).transpose()?;
+                        arguments.push(FunctionHeadDestructuredArgument::new_span(
+                            name,
+                            default,
+                            formal_span,
+                        ));
+                    }
+                    Rule::ellipsis => ellipsis = true,
+                    _ => unreachable!("Unhandled function head part {:?}", inner.as_rule()),
+                }
+            }
+            Ok(FunctionHead::FunctionHeadDestructured(FunctionHeadDestructured::new_span(
+                ellipsis,
+                identifier,
+                identifier_before,
+                arguments,
+                span,
+            )))
+        }
+        _ => unreachable!("Unhandled function head {:?}", pair.as_rule()),
+    }
+}
+
+/// Rejects chains of non-associative operators such as `a < b < c`,
+/// `a == b != c` or `a ? b ? c`, which Nix refuses to parse without
+/// parentheses. Prefix operators all bind tighter than the non-associative
+/// infix ones, so they never separate two operators of a chain.
+fn check_associativity(pair: &Pair<Rule>) -> Result<(), Box<Diagnostic>> {
+    let rules: Vec<Rule> = pair.clone().into_inner().map(|p| p.as_rule()).collect();
+    if let Some(index) = rules
+        .windows(2)
+        .position(|w| w == [Rule::has_attribute, Rule::has_attribute])
+    {
+        let mut inner = pair.clone().into_inner().skip(index);
+        let first = Span::from_pest_span(inner.next().unwrap().as_span());
+        let second = Span::from_pest_span(inner.next().unwrap().as_span());
+        return Err(Diagnostic::error(
+            codes::NON_ASSOCIATIVE_CHAIN,
+            "operator `?` cannot be chained".to_string(),
+            second,
+        )
+        .with_secondary(first, "first `?` here".to_string())
+        .with_help("add parentheses".to_string()).into());
+    }
+
+    let operators = pair
+        .clone()
+        .into_inner()
+        .filter(|p| {
+            !matches!(
+                p.as_rule(),
+                Rule::application | Rule::op_not | Rule::op_negate | Rule::has_attribute
+            )
+        })
+        .map(|p| Ok((parse_operator(p.clone())?, p)))
+        .collect::<Result<Vec<(Operator, Pair<Rule>)>, Box<Diagnostic>>>()?;
+
+    for (index, (operator, op_pair)) in operators.iter().enumerate() {
+        if operator.associativity() != Associativity::None {
+            continue;
+        }
+        // The next operator that binds at most as tightly decides whether the
+        // two share an operand.
+        let next = operators[index + 1..]
+            .iter()
+            .find(|(other, _)| other.precedence() >= operator.precedence());
+        if let Some((other, other_pair)) = next
+            && other.precedence() == operator.precedence()
+        {
+            return Err(Diagnostic::error(
+                codes::NON_ASSOCIATIVE_CHAIN,
+                format!("operator `{}` cannot be chained with `{}`", other.render(), op_pair.as_str()),
+                Span::from_pest_span(other_pair.as_span()),
+            )
+            .with_secondary(
+                Span::from_pest_span(op_pair.as_span()),
+                format!("first `{}` here", op_pair.as_str()),
+            )
+            .with_help("add parentheses".to_string()).into());
+        }
+    }
+    Ok(())
+}
+
+/// The span of `pair` up to its last token. pest also gives a rule the
+/// whitespace and comments it skips after that, which belong to whatever
+/// follows.
+fn span_of(pair: &Pair<Rule>) -> Span {
+    let span = pair.as_span();
+    let end = code_end(pair);
+    Span::from_pest_span(pest::Span::new(pair.get_input(), span.start(), end).unwrap())
+}
+
+fn code_end(pair: &Pair<Rule>) -> usize {
+    let (input, stop) = (pair.get_input(), pair.as_span().end());
+    let Some(last) = pair.clone().into_inner().last() else {
+        return stop;
+    };
+    // Whatever follows the last inner pair is tokens of the rule itself,
+    // such as `)` or `;`, and skipped whitespace and comments.
+    let mut end = code_end(&last);
+    let mut offset = end;
+    while offset < stop {
+        let rest = &input[offset..stop];
+        if rest.starts_with('#') {
+            offset += rest.find('\n').unwrap_or(rest.len());
+        } else if rest.starts_with("/*") {
+            offset += rest.find("*/").map_or(rest.len(), |index| index + 2);
+        } else {
+            let c = rest.chars().next().unwrap();
+            offset += c.len_utf8();
+            if !c.is_whitespace() {
+                end = offset;
+            }
+        }
+    }
+    end
+}
+
+fn parse_operator(pair: Pair<Rule>) -> Result<Operator, Box<Diagnostic>> {
     use super::ast::operators::*;
-    let inner = pair.into_inner().next().unwrap();
-    match inner.as_rule() {
+    let replacement = match pair.as_rule() {
+        Rule::op_impl_legacy => Some(("->", "logical implication")),
+        Rule::op_update_legacy => Some(("//", "attribute set update")),
+        _ => None,
+    };
+    if let Some((token, name)) = replacement {
+        return Err(Diagnostic::error(
+            codes::LEGACY_OPERATOR,
+            format!("`{}` is not a Nix operator", pair.as_str()),
+            Span::from_pest_span(pair.as_span()),
+        )
+        .with_help(format!("write `{}` for {}", token, name)).into());
+    }
+    Ok(match pair.as_rule() {
         Rule::op_add => Operator::Addition(Addition),
         Rule::op_sub => Operator::Subtraction(Subtraction),
         Rule::op_mul => Operator::Multiplication(Multiplication),
@@ -78,7 +643,7 @@ fn parse_operator(pair: pest::iterators::Pair<Rule>) -> Operator {
         Rule::op_impl   => Operator::Implication(Implication),
         Rule::op_update => Operator::Update(Update),
         Rule::op_not => Operator::Not(Not),
-        _ => unreachable!("Unhandled operator {:?}", inner.as_rule()),
-    }
+        Rule::op_negate => Operator::Negate(Negate),
+        _ => unreachable!("Unhandled operator {:?}", pair.as_rule()),
+    })
 }
-
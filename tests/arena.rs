@@ -0,0 +1,77 @@
+use nix_lens::parser::ast::arena::{Arena, NodeId, NodeKind};
+use nix_lens::parser::parser::parse;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(arena: &Arena, ids: impl Iterator<Item = NodeId>) -> Vec<String> {
+        ids.map(|id| format!("{:?}", arena[id].kind)).collect()
+    }
+
+    #[test]
+    fn test_structure() {
+        let ast = parse("{ a = f 1; inherit b; }").unwrap();
+        let arena = Arena::from_expressions(&ast);
+        assert_eq!(arena.len(), 8);
+        let root = arena.roots()[0];
+        assert!(matches!(arena[root].kind, NodeKind::AttrSet { recursive: false }));
+        assert_eq!(arena.parent(root), None);
+
+        let bindings: Vec<NodeId> = arena.children(root).collect();
+        assert_eq!(
+            kinds(&arena, bindings.iter().copied()),
+            ["BindingKeyValue", "BindingInherit { has_from: false }"]
+        );
+        assert_eq!(
+            kinds(&arena, arena.children(bindings[0])),
+            ["Identifier(\"a\")", "FunctionApplication"]
+        );
+
+        let last = arena.descendants(root).last().unwrap();
+        assert!(matches!(arena[last].kind, NodeKind::Identifier(ref b) if b == "b"));
+        assert_eq!(arena.ancestors(last).collect::<Vec<_>>(), [bindings[1], root]);
+    }
+
+    #[test]
+    fn test_descendants_in_source_order() {
+        let source = "x: if x then [ 1 2 ] else y.z or 3";
+        let ast = parse(source).unwrap();
+        let arena = Arena::from(&ast[0]);
+        let texts: Vec<&str> = arena
+            .descendants(arena.roots()[0])
+            .map(|id| &source[arena[id].span.range()])
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                source,
+                "if x then [ 1 2 ] else y.z or 3",
+                "x",
+                "[ 1 2 ]",
+                "1",
+                "2",
+                "y.z or 3",
+                "y",
+                "z",
+                "3",
+            ]
+        );
+        assert!(matches!(
+            arena[arena.roots()[0]].kind,
+            NodeKind::Function { formals: None, .. }
+        ));
+    }
+
+    #[test]
+    fn test_side_tables() {
+        let ast = parse("let a = 1; b = a; in b").unwrap();
+        let arena = Arena::from_expressions(&ast);
+        let mut depth = vec![0; arena.len()];
+        for id in arena.ids() {
+            depth[id.index()] = arena.ancestors(id).count();
+        }
+        assert_eq!(depth.iter().max(), Some(&2));
+        assert_eq!(arena.ids().count(), arena.len());
+    }
+}
@@ -0,0 +1,111 @@
+use nix_lens::parser::cst::{EditError, TextEdit, TokenKind, apply_edits, parse_cst};
+use nix_lens::parser::parser::Rule;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCES: &[&str] = &[
+        "x",
+        "  1   +2 # trailing\n",
+        "{ pkgs ? import <nixpkgs> { }, ... }:\n\nwith pkgs;\n\n/* block\n   comment */\nlet\n  inherit (lib) mkIf;\n  xs = [ 1 2.5 \"a ${b} c\" ];\nin\n  rec { a = xs; b.c = a.d or null; }\n",
+        "''\n  indented ${ x # inside\n  }\n  $${literal} '''\n''",
+        "{ }",
+        "[ /* empty */ ]",
+        "a: b@{ c, ... }: ./foo/${a}.nix",
+        "if a ? b then -1 else !c || d -> e // f",
+        "\t{\r\n  x = https://example.com/a?b=c;\r\n}\r\n",
+    ];
+
+    #[test]
+    fn test_round_trip() {
+        for source in SOURCES {
+            let cst = parse_cst(source).unwrap();
+            assert_eq!(cst.text(), *source);
+            assert_eq!(cst.range, 0..source.len());
+        }
+    }
+
+    #[test]
+    fn test_tokens_cover_source() {
+        for source in SOURCES {
+            let cst = parse_cst(source).unwrap();
+            let mut offset = 0;
+            for token in cst.tokens() {
+                assert_eq!(token.offset, offset, "{:?} in {:?}", token, source);
+                assert_eq!(&source[token.range()], token.text);
+                offset = token.range().end;
+            }
+            assert_eq!(offset, source.len());
+        }
+    }
+
+    #[test]
+    fn test_token_kinds() {
+        let cst = parse_cst("let a = 1; /* c */ in a # end").unwrap();
+        let kinds: Vec<(TokenKind, &str)> = cst
+            .tokens()
+            .into_iter()
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| (t.kind, t.text.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (TokenKind::Symbol, "let"),
+                (TokenKind::Text, "a"),
+                (TokenKind::Symbol, "="),
+                (TokenKind::Text, "1"),
+                (TokenKind::Symbol, ";"),
+                (TokenKind::Comment, "/* c */"),
+                (TokenKind::Symbol, "in"),
+                (TokenKind::Text, "a"),
+                (TokenKind::Comment, "# end"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edit_touched_range_only() {
+        let source = "{\n  # keep me\n  foo   =  1;\n  bar = foo;\n}\n";
+        let cst = parse_cst(source).unwrap();
+        let edits: Vec<TextEdit> = cst
+            .tokens()
+            .into_iter()
+            .filter(|t| t.kind == TokenKind::Text && t.text == "foo")
+            .map(|t| TextEdit::new(t.range(), "baz".to_string()))
+            .collect();
+        assert_eq!(edits.len(), 2);
+        assert_eq!(
+            apply_edits(source, &edits).unwrap(),
+            "{\n  # keep me\n  baz   =  1;\n  bar = baz;\n}\n"
+        );
+
+        let node = cst.covering_node(edits[0].range.clone());
+        assert_eq!(node.rule, Rule::identifier);
+        assert_eq!(node.text(), "foo");
+    }
+
+    #[test]
+    fn test_invalid_edits() {
+        let edit = |range, text: &str| TextEdit::new(range, text.to_string());
+        assert_eq!(
+            apply_edits("abcdef", &[edit(3..5, "x"), edit(1..4, "y")]),
+            Err(EditError::Overlapping(1..4, 3..5))
+        );
+        assert_eq!(
+            apply_edits("abc", &[edit(2..7, "x")]),
+            Err(EditError::InvalidRange(2..7))
+        );
+        assert_eq!(apply_edits("é", &[edit(1..2, "x")]), Err(EditError::InvalidRange(1..2)));
+        let reversed = std::ops::Range { start: 2, end: 1 };
+        assert_eq!(
+            apply_edits("abc", &[edit(reversed.clone(), "x")]),
+            Err(EditError::InvalidRange(reversed))
+        );
+
+        // Edits that only touch at their ends, and insertions, are fine.
+        let edits = [edit(3..3, "-"), edit(1..3, "x"), edit(3..4, "y")];
+        assert_eq!(apply_edits("abcde", &edits).unwrap(), "ax-ye");
+    }
+}
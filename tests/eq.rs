@@ -0,0 +1,88 @@
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use nix_lens::parser::ast::*;
+use nix_lens::parser::parser::{parse, parse_recovering};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one(input: &str) -> Expression {
+        parse(input).unwrap().remove(0)
+    }
+
+    fn hash(expression: &Expression) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        expression.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_ignores_spans() {
+        let a = one("f (a + 1) { x = [ y ]; }");
+        let b = one("f  (a+1)\n  { x = [y]; }");
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert!(!a.spanned_eq(&b));
+        assert!(a.spanned_eq(&a.clone()));
+    }
+
+    #[test]
+    fn test_ignores_comments() {
+        let a = one("{ a = 1; }");
+        let b = one("{\n  # one\n  a = 1; /* c */\n}");
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+
+        let c = one("{ a = 1; # one\n}");
+        let d = one("{ a = 1; # two\n}");
+        assert_eq!(c, d);
+        assert!(!c.spanned_eq(&d));
+    }
+
+    #[test]
+    fn test_structure_differs() {
+        assert_ne!(one("a + 1"), one("a - 1"));
+        assert_ne!(one("a + 1"), one("1 + a"));
+        assert_ne!(one("{ a = 1; }"), one("rec { a = 1; }"));
+        assert_ne!(one("./a"), one("/a"));
+        assert_ne!(one("x: x"), one("{ x }: x"));
+        assert_ne!(one("{ x, ... }: x"), one("{ x }: x"));
+        assert_ne!(one("7"), one("007"));
+        assert_ne!(one("\"a\""), one("''a''"));
+    }
+
+    #[test]
+    fn test_parsed_equals_built() {
+        let built = Expression::BinaryOperation(
+            BinaryOperation::new(
+                Expression::Identifier(Identifier::new("a".to_string())),
+                operators::Operator::Addition(operators::Addition),
+                Expression::Float(Float::new(2.5).unwrap()),
+            )
+            .unwrap(),
+        );
+        assert_eq!(one("a + 2.5"), built);
+        assert!(!one("a + 2.5").spanned_eq(&built));
+    }
+
+    #[test]
+    fn test_deduplicate() {
+        let ast = one("[ (a + 1) (a + 1) (a + 2) (a +  1) ]");
+        let Expression::List(list) = ast else { panic!() };
+        let unique: HashSet<&Expression> = list.elements.iter().collect();
+        assert_eq!(unique.len(), 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        let a = parse_recovering("{ a = ; }").expressions;
+        let b = parse_recovering("{  a =  ; }").expressions;
+        let c = parse_recovering("{ a = 1 }").expressions;
+        assert_eq!(a, b);
+        assert!(!a.spanned_eq(&b));
+        assert_ne!(a, c);
+    }
+}
@@ -0,0 +1,309 @@
+use std::process::Command;
+
+use nix_lens::format::{FormatOptions, TrailingComma, format, format_expression};
+use nix_lens::parser::ast::*;
+use nix_lens::parser::parser::parse;
+
+const PACKAGE: &str = r#"# A package
+{ lib, stdenv, fetchurl, pkg-config, openssl ? null, enableFoo ? false, ... }:
+
+let
+  version = "1.2.3";
+  # the source
+  src = fetchurl { url = "https://example.org/foo-${version}.tar.gz"; sha256 = "0000000000000000000000000000000000000000000000000000"; };
+
+
+  helper = x: y: if x > y then x else if x == y then 0 else y;
+in
+stdenv.mkDerivation rec {
+  pname = "foo";
+  inherit version src;
+  nativeBuildInputs = [ pkg-config ];
+  buildInputs = lib.optional (openssl != null) openssl ++ lib.optionals enableFoo [ openssl pkg-config stdenv.cc.cc.lib ];
+  postPatch = ''
+    substituteInPlace Makefile \
+      --replace gcc cc
+  '';
+  doCheck = true; # tests pass
+  meta = with lib; { description = "Foo"; license = licenses.mit; platforms = platforms.unix; };
+}
+"#;
+
+fn with_width(width: usize) -> FormatOptions {
+    FormatOptions { width, ..FormatOptions::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Formats `input`, checking that the result keeps its meaning and is
+    /// left alone by a second run.
+    fn check(input: &str, options: &FormatOptions) -> String {
+        let formatted = format(input, options).unwrap();
+        assert_eq!(parse(&formatted).unwrap(), parse(input).unwrap(), "{}", formatted);
+        assert_eq!(format(&formatted, options).unwrap(), formatted);
+        formatted
+    }
+
+    #[test]
+    fn test_package() {
+        assert_eq!(
+            check(PACKAGE, &FormatOptions::default()),
+            r#"# A package
+{ lib, stdenv, fetchurl, pkg-config, openssl ? null, enableFoo ? false, ... }:
+
+let
+  version = "1.2.3";
+  # the source
+  src = fetchurl {
+    url = "https://example.org/foo-${version}.tar.gz";
+    sha256 = "0000000000000000000000000000000000000000000000000000";
+  };
+
+  helper = x: y: if x > y then x else if x == y then 0 else y;
+in
+stdenv.mkDerivation rec {
+  pname = "foo";
+  inherit version src;
+  nativeBuildInputs = [ pkg-config ];
+  buildInputs =
+    lib.optional (openssl != null) openssl
+    ++ lib.optionals enableFoo [ openssl pkg-config stdenv.cc.cc.lib ];
+  postPatch = ''
+    substituteInPlace Makefile \
+      --replace gcc cc
+  '';
+  doCheck = true; # tests pass
+  meta = with lib; { description = "Foo"; license = licenses.mit; platforms = platforms.unix; };
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_idempotent_at_any_width() {
+        for width in [10, 20, 40, 60, 80, 100, 200] {
+            check(PACKAGE, &with_width(width));
+            for source in [
+                "(f x) # c",
+                "(a: a) # c",
+                "[ (a b) # c\n]",
+                "assert a; # c\nb",
+                "{ inherit (x) # c\n  a b; }",
+                "{ a = f x; # c\n}",
+            ] {
+                check(source, &with_width(width));
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_line_when_it_fits() {
+        let options = FormatOptions::default();
+        assert_eq!(check("{a=1;b=[1 2];}", &options), "{ a = 1; b = [ 1 2 ]; }\n");
+        assert_eq!(check("let\n  a = 1;\nin\n  a", &options), "let a = 1; in a\n");
+        assert_eq!(check("if a then b else c", &options), "if a then b else c\n");
+        assert_eq!(check("{ }", &options), "{ }\n");
+        assert_eq!(check("[ ]", &options), "[ ]\n");
+        assert_eq!(check("{ inherit ( x ) a; }", &options), "{ inherit (x) a; }\n");
+    }
+
+    #[test]
+    fn test_breaking() {
+        let options = with_width(20);
+        assert_eq!(
+            check("{ alpha = 1; beta = [ 1 2 3 4 5 ]; }", &options),
+            "{\n  alpha = 1;\n  beta = [\n    1\n    2\n    3\n    4\n    5\n  ];\n}\n"
+        );
+        assert_eq!(
+            check("if condition then alpha else beta", &options),
+            "if condition then\n  alpha\nelse\n  beta\n"
+        );
+        assert_eq!(
+            check("let alpha = 1; in alpha + alpha", &options),
+            "let\n  alpha = 1;\nin\nalpha + alpha\n"
+        );
+        assert_eq!(
+            check("{ value = alpha + beta + gamma; }", &options),
+            "{\n  value =\n    alpha\n    + beta\n    + gamma;\n}\n"
+        );
+        assert_eq!(
+            check("f { alpha = 1; beta = 2; }", &options),
+            "f {\n  alpha = 1;\n  beta = 2;\n}\n"
+        );
+        assert_eq!(
+            check("x: (alpha beta) + (gamma delta)", &options),
+            "x:\nalpha beta\n+ gamma delta\n"
+        );
+    }
+
+    #[test]
+    fn test_indent() {
+        let options = FormatOptions { width: 10, indent: 4, ..FormatOptions::default() };
+        assert_eq!(
+            check("{ a = [ 1 2 3 ]; }", &options),
+            "{\n    a = [\n        1\n        2\n        3\n    ];\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_trailing_comma() {
+        let source = "{ alpha, beta ? 1 }: alpha";
+        let narrow = |trailing_comma| FormatOptions { width: 12, indent: 2, trailing_comma };
+
+        let vertical = "{\n  alpha,\n  beta ? 1,\n}:\nalpha\n";
+        assert_eq!(check(source, &narrow(TrailingComma::Vertical)), vertical);
+        assert_eq!(check(source, &narrow(TrailingComma::Always)), vertical);
+        let never = "{\n  alpha,\n  beta ? 1\n}:\nalpha\n";
+        assert_eq!(check(source, &narrow(TrailingComma::Never)), never);
+
+        let wide = |trailing_comma| FormatOptions { trailing_comma, ..FormatOptions::default() };
+        assert_eq!(check(source, &wide(TrailingComma::Vertical)), "{ alpha, beta ? 1 }: alpha\n");
+        assert_eq!(check(source, &wide(TrailingComma::Always)), "{ alpha, beta ? 1, }: alpha\n");
+        // `...` must come last, so it never takes a comma.
+        assert_eq!(check("{ a, ... }: a", &wide(TrailingComma::Always)), "{ a, ... }: a\n");
+        assert_eq!(check("args@{ a }: a", &wide(TrailingComma::Never)), "args@{ a }: a\n");
+    }
+
+    #[test]
+    fn test_parentheses() {
+        let options = FormatOptions::default();
+        assert_eq!(check("((a + b)) * c", &options), "(a + b) * c\n");
+        assert_eq!(check("(f x) (y: y) (a.b or c)", &options), "(f x) (y: y) a.b or c\n");
+        assert_eq!(check("a - (b - c)", &options), "a - (b - c)\n");
+        assert_eq!(check("(a ++ b) ++ c", &options), "(a ++ b) ++ c\n");
+    }
+
+    #[test]
+    fn test_comments() {
+        let options = FormatOptions::default();
+        assert_eq!(
+            check("{ a = 1; # one\n  b = 2; }", &options),
+            "{\n  a = 1; # one\n  b = 2;\n}\n"
+        );
+        assert_eq!(check("[ 1 /* one */ 2 ]", &options), "[ 1 /* one */ 2 ]\n");
+        assert_eq!(check("{\n  # about a\n  a = 1;\n}", &options), "{\n  # about a\n  a = 1;\n}\n");
+        assert_eq!(check("1 # end", &options), "1 # end\n");
+        assert_eq!(check("{ a = x # c\n; }", &options), "{\n  a =\n    x # c\n  ;\n}\n");
+        assert_eq!(check("[ # first\n 1 ]", &options), "[ # first\n  1\n]\n");
+        assert_eq!(check("assert a; # c\nb", &options), "assert a; # c\nb\n");
+        assert_eq!(check("with a; # c\nb", &options), "with a; # c\nb\n");
+        assert_eq!(
+            check("{ inherit (x) # c\n a b; }", &options),
+            "{\n  inherit (x) # c\n    a\n    b;\n}\n"
+        );
+        assert_eq!(check("{ }\n# end", &options), "{ }\n# end\n");
+        assert_eq!(check("[ /* none */ ]", &options), "[ /* none */ ]\n");
+        assert_eq!(check("{ a = { # none\n}; }", &options), "{\n  a = {\n    # none\n  };\n}\n");
+    }
+
+    #[test]
+    fn test_comments_on_formals() {
+        let options = FormatOptions::default();
+        let source = "{ stdenv\n, fetchurl\n# Enable the thing\n\
+                      , enableFoo ? false\n, bar # the bar\n}:\nx";
+        assert_eq!(
+            check(source, &options),
+            "{\n  stdenv,\n  fetchurl,\n  # Enable the thing\n  \
+             enableFoo ? false,\n  bar, # the bar\n}:\nx\n"
+        );
+        assert_eq!(check("{ a, # c\n b }: a", &options), "{\n  a, # c\n  b,\n}:\na\n");
+        assert_eq!(check("{ a /* c */, ... }: a", &options), "{ a, /* c */ ... }: a\n");
+        let options = FormatOptions { trailing_comma: TrailingComma::Never, ..options };
+        assert_eq!(check("{ a ? 1 # c\n}: a", &options), "{\n  a ? 1 # c\n}:\na\n");
+    }
+
+    #[test]
+    fn test_blank_lines() {
+        let options = FormatOptions::default();
+        assert_eq!(check("[\n  a\n\n\n\n  b\n  c\n]", &options), "[\n  a\n\n  b\n  c\n]\n");
+        assert_eq!(
+            check("{ a = 1;\n\n  # b\n  b = 2; }", &options),
+            "{\n  a = 1;\n\n  # b\n  b = 2;\n}\n"
+        );
+        // A `(` on a line of its own is not a blank line.
+        assert_eq!(check("[\n  a\n  (\n    b c\n  )\n]", &options), "[ a (b c) ]\n");
+    }
+
+    #[test]
+    fn test_strings_are_verbatim() {
+        let options = with_width(10);
+        for source in [
+            "\"esc \\\" \\${not} \\n ü ${ a  +  b }\"",
+            "''\n  multi\n    line ''${x} ${y}\n''",
+            "./a/${b}.nix",
+        ] {
+            let formatted = check(source, &options);
+            assert_eq!(parse(&formatted).unwrap(), parse(source).unwrap());
+        }
+        assert_eq!(check("{ a = ''\n  x\n''; }", &options), "{\n  a = ''\n  x\n'';\n}\n");
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let error = format("{ a = 1;", &FormatOptions::default()).unwrap_err();
+        assert_eq!(error.code, "NL0002");
+    }
+
+    #[test]
+    fn test_format_expression() {
+        let function = Expression::Function(Function::new(
+            FunctionHead::FunctionHeadSimple(FunctionHeadSimple::new(Identifier::new(
+                "x".to_string(),
+            ))),
+            Expression::List(List::new(vec![
+                Expression::Identifier(Identifier::new("x".to_string())),
+                Expression::Integer(Integer::new(-1)),
+            ])),
+        ));
+        let options = FormatOptions::default();
+        assert_eq!(format_expression(&function, &options).unwrap(), "x: [ x (-1) ]");
+    }
+
+    fn nixlens(args: &[&str], input: &str) -> (i32, String) {
+        use std::io::Write;
+        let mut child = Command::new(env!("CARGO_BIN_EXE_nixlens"))
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        let text = String::from_utf8(output.stdout).unwrap()
+            + &String::from_utf8(output.stderr).unwrap();
+        (output.status.code().unwrap(), text)
+    }
+
+    #[test]
+    fn test_cli() {
+        assert_eq!(nixlens(&["fmt"], "{a=1;}"), (0, "{ a = 1; }\n".to_string()));
+        assert_eq!(
+            nixlens(&["fmt", "--width", "10", "--indent", "4"], "{a=1;b=2;}"),
+            (0, "{\n    a = 1;\n    b = 2;\n}\n".to_string())
+        );
+        assert_eq!(nixlens(&["fmt", "--check"], "{ a = 1; }\n").0, 0);
+        assert_eq!(nixlens(&["fmt", "--check"], "{a=1;}"), (1, "<stdin>\n".to_string()));
+        assert_eq!(nixlens(&["fmt", "--trailing-comma", "sometimes"], "").0, 2);
+
+        let (status, output) = nixlens(&["fmt"], "{ a = ; }");
+        assert_eq!(status, 1);
+        assert!(output.contains("error[NL0001]"), "{}", output);
+    }
+
+    #[test]
+    fn test_cli_files() {
+        let path = std::env::temp_dir().join(format!("nixlens-fmt-{}.nix", std::process::id()));
+        std::fs::write(&path, "[1 2]").unwrap();
+        let file = path.to_str().unwrap();
+
+        assert_eq!(nixlens(&["fmt", "--check", file], ""), (1, format!("{}\n", file)));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[1 2]");
+        assert_eq!(nixlens(&["fmt", file], ""), (0, String::new()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[ 1 2 ]\n");
+        assert_eq!(nixlens(&["fmt", "--check", file], "").0, 0);
+        std::fs::remove_file(path).unwrap();
+    }
+}
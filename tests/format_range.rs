@@ -0,0 +1,186 @@
+use nix_lens::format::{FormatOptions, format_on_type, format_range};
+use nix_lens::parser::cst::{TextEdit, apply_edits};
+use nix_lens::parser::parser::parse;
+
+const SET: &str = "{
+  keep   =   [ 1  2 ];
+  value = {x=1;    y = 2;};
+  other   =   3;
+}
+";
+
+fn with_width(width: usize) -> FormatOptions {
+    FormatOptions { width, ..FormatOptions::default() }
+}
+
+/// Formats the part of `source` marked with `«` and `»`.
+fn format_marked(source: &str, options: &FormatOptions) -> (String, Vec<TextEdit>) {
+    let start = source.find('«').unwrap();
+    let source = source.replacen('«', "", 1);
+    let end = source.find('»').unwrap();
+    let source = source.replacen('»', "", 1);
+    let edits = format_range(&source, start..end, options);
+    (apply_edits(&source, &edits).unwrap(), edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_the_selected_binding() {
+        let start = SET.find("value").unwrap();
+        let (formatted, _) = format_marked(
+            &format!("{}«{}»{}", &SET[..start], &SET[start..start + 5], &SET[start + 5..]),
+            &FormatOptions::default(),
+        );
+        assert_eq!(
+            formatted,
+            "{
+  keep   =   [ 1  2 ];
+  value = { x = 1; y = 2; };
+  other   =   3;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_minimal_edits() {
+        let source = "[ a  b ]";
+        let edits = format_range(source, 2..3, &FormatOptions::default());
+        assert_eq!(edits, vec![]);
+
+        let source = "{ a=1; b = 2; }";
+        let edits = format_range(source, 2..3, &FormatOptions::default());
+        assert_eq!(
+            edits,
+            vec![TextEdit::new(3..3, " ".to_string()), TextEdit::new(4..4, " ".to_string())]
+        );
+
+        let source = "x:   ((a +  b))";
+        let edits = format_range(source, 7..8, &FormatOptions::default());
+        assert_eq!(
+            edits,
+            vec![
+                TextEdit::new(2..7, " ".to_string()),
+                TextEdit::new(10..12, " ".to_string()),
+                TextEdit::new(13..15, String::new()),
+            ]
+        );
+        assert_eq!(apply_edits(source, &edits).unwrap(), "x: a + b");
+    }
+
+    #[test]
+    fn test_several_bindings() {
+        let (formatted, _) = format_marked(
+            "{ a=1; b=«2; c=3;»   d=4; }",
+            &FormatOptions::default(),
+        );
+        assert_eq!(formatted, "{ a=1; b = 2; c = 3;   d=4; }");
+
+        // Parentheses around an element belong to the list.
+        let (formatted, _) = format_marked(
+            "[ a  «((b  +  c))  (d   +   e)»  f ]",
+            &FormatOptions::default(),
+        );
+        assert_eq!(formatted, "[ a  ((b + c))  (d + e)  f ]");
+    }
+
+    #[test]
+    fn test_innermost_binding() {
+        let source = SET.replacen("x=1", "x=«»1", 1);
+        let (formatted, _) = format_marked(&source, &FormatOptions::default());
+        assert_eq!(formatted, SET.replacen("x=1", "x = 1", 1));
+
+        // Outside of any binding the whole expression is formatted.
+        let source = "let a = {x=1;}; in «a   +  a»";
+        let (formatted, _) = format_marked(source, &FormatOptions::default());
+        assert_eq!(formatted, "let a = { x = 1; }; in a + a");
+    }
+
+    #[test]
+    fn test_indentation() {
+        let source = SET.replacen("value", "«value»", 1);
+        let (formatted, _) = format_marked(&source, &with_width(20));
+        assert_eq!(
+            formatted,
+            "{
+  keep   =   [ 1  2 ];
+  value = {
+    x = 1;
+    y = 2;
+  };
+  other   =   3;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_comments_stay_put() {
+        let source = "{\n  # about a\n  a=«1»; # one\n  b = [ /* none */ ];\n}";
+        let (formatted, _) = format_marked(source, &FormatOptions::default());
+        assert_eq!(formatted, "{\n  # about a\n  a = 1; # one\n  b = [ /* none */ ];\n}");
+
+        let source = "[ [   /* none */ ] ]";
+        let edits = format_range(source, 2..2, &FormatOptions::default());
+        assert_eq!(apply_edits(source, &edits).unwrap(), "[ [ /* none */ ] ]");
+    }
+
+    #[test]
+    fn test_syntax_errors_elsewhere() {
+        let (formatted, _) = format_marked("{ a=«1»; b = ; c=2; }", &FormatOptions::default());
+        assert_eq!(formatted, "{ a = 1; b = ; c=2; }");
+        assert_eq!(format_range("{ a = ; }", 0..9, &FormatOptions::default()), vec![]);
+    }
+
+    #[test]
+    fn test_any_range_keeps_the_tree() {
+        let source = "{ pkgs ? import <nixpkgs> {}, ... }:\nlet\n  inherit (pkgs)   lib;\n  \
+            xs = [ 1 (2+3)   \"s\" ];  # xs\nin\n  pkgs.mkShell { buildInputs = with pkgs; \
+            [ git ];  shellHook = ''\n    echo hi\n  ''; }\n";
+        let options = with_width(30);
+        for start in (0..source.len()).step_by(7) {
+            for end in [start, (start + 13).min(source.len())] {
+                let edits = format_range(source, start..end, &options);
+                let formatted = apply_edits(source, &edits).unwrap();
+                assert_eq!(parse(&formatted).unwrap(), parse(source).unwrap(), "{}", formatted);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ranges_out_of_bounds() {
+        let options = FormatOptions::default();
+        let source = "{ a=1; }";
+        let edits = format_range(source, 2..100, &options);
+        assert_eq!(apply_edits(source, &edits).unwrap(), "{ a = 1; }");
+        assert_eq!(format_range(source, 50..100, &options), vec![]);
+        assert_eq!(format_range(source, std::ops::Range { start: 4, end: 3 }, &options), vec![]);
+    }
+
+    #[test]
+    fn test_format_on_type() {
+        let options = FormatOptions::default();
+        let source = "{\n  a=1;\n  b   =   {c=[1   2];};\n}";
+        let offset = source.find("};").unwrap() + 2;
+        let edits = format_on_type(source, offset, ';', &options);
+        assert_eq!(apply_edits(source, &edits).unwrap(), "{\n  a=1;\n  b = { c = [ 1 2 ]; };\n}");
+
+        // The `;` closing the inner binding formats just that binding.
+        let offset = source.find("];").unwrap() + 2;
+        let edits = format_on_type(source, offset, ';', &options);
+        assert_eq!(apply_edits(source, &edits).unwrap(), "{\n  a=1;\n  b   =   {c = [ 1 2 ];};\n}");
+
+        // While the rest is still being typed.
+        let source = "let\n  x   =   1;\n  y = \nin x";
+        let offset = source.find("1;").unwrap() + 2;
+        let edits = format_on_type(source, offset, ';', &options);
+        assert_eq!(apply_edits(source, &edits).unwrap(), "let\n  x = 1;\n  y = \nin x");
+
+        assert_eq!(format_on_type(source, offset, '}', &options), vec![]);
+        assert_eq!(format_on_type(source, offset - 1, ';', &options), vec![]);
+        assert_eq!(format_on_type("with   x; x", 9, ';', &options), vec![]);
+    }
+}
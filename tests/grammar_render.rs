@@ -1,4 +1,5 @@
 use nix_lens::parser::ast::*;
+use nix_lens::parser::parser::parse;
 
 #[cfg(test)]
 mod tests {
@@ -9,12 +10,18 @@ mod tests {
         let id = Identifier::new("foo".to_string());
         assert_eq!(id.render(), "foo");
 
-        let int = Integer::new("42".to_string());
+        let int = Integer::new(42);
         assert_eq!(int.render(), "42");
 
-        let float = Float::new("3.14".to_string());
-        assert_eq!(float.render(), "3.14");
+        let float = Float::new(2.5).unwrap();
+        assert_eq!(float.render(), "2.5");
 
+        let whole_float = Float::new(10.0).unwrap();
+        assert_eq!(whole_float.render(), "10.0");
+
+        assert!(Float::new(f64::NAN).is_err());
+        assert!(Float::new(f64::INFINITY).is_err());
+        assert!(Float::new(f64::NEG_INFINITY).is_err());
     }
 
     #[test]
@@ -34,27 +41,38 @@ mod tests {
         assert_eq!(operators::Not.render(), "!");
         assert_eq!(operators::Negate.render(), "-");
         assert_eq!(operators::Concatenation.render(), "++");
-        assert_eq!(operators::Update.render(), ":=");
+        assert_eq!(operators::Implication.render(), "->");
+        assert_eq!(operators::Update.render(), "//");
     }
 
     // Functions
     #[test]
     fn test_function_rendering() {
-        let arg = FunctionHeadDestructuredArgument {
-            identifier: "arg".to_string(),
-            default: Some(Expression::Integer(Integer::new("42".to_string()))),
-        };
+        let arg = FunctionHeadDestructuredArgument::new(
+            Identifier::new("arg".to_string()),
+            Some(Expression::Integer(Integer::new(42))),
+        );
         assert_eq!(arg.render().unwrap(), "arg ? 42");
 
         let simple_head = FunctionHeadSimple::new(Identifier::new("arg".to_string()));
         assert_eq!(simple_head.render(), "arg");
 
         let func = Function::new(
-            Expression::Identifier(Identifier::new("arg".to_string())),
+            FunctionHead::FunctionHeadSimple(simple_head),
             Expression::Identifier(Identifier::new("body".to_string())),
         );
         assert_eq!(func.render().unwrap(), "arg: body");
 
+        let destructured_head = FunctionHeadDestructured::new(
+            true,
+            Some(Identifier::new("args".to_string())),
+            vec![
+                arg,
+                FunctionHeadDestructuredArgument::new(Identifier::new("b".to_string()), None),
+            ],
+        );
+        assert_eq!(destructured_head.render().unwrap(), "{ arg ? 42, b, ... }@args");
+
         let app = FunctionApplication::new(
             Expression::Identifier(Identifier::new("func".to_string())),
             vec![Expression::Identifier(Identifier::new("arg".to_string()))]
@@ -68,13 +86,13 @@ mod tests {
             Expression::Identifier(Identifier::new("a".to_string())),
             Expression::Identifier(Identifier::new("b".to_string())),
         ]);
-        assert_eq!(list.render().unwrap(), "[a, b]");
+        assert_eq!(list.render().unwrap(), "[ a b ]");
 
         let map = AttrSet::new(
             false,
             vec![Expression::BindingKeyValue(BindingKeyValue::new(
-                Expression::Identifier(Identifier::new("a".to_string())),
-                Expression::Integer(Integer::new("1".to_string())),
+                AttrPath::new(vec![Expression::Identifier(Identifier::new("a".to_string()))]),
+                Expression::Integer(Integer::new(1)),
             ))],
         );
         assert_eq!(map.render().unwrap(), "{ a = 1; }");
@@ -82,8 +100,8 @@ mod tests {
         let rec_map = AttrSet::new(
             true,
             vec![Expression::BindingKeyValue(BindingKeyValue::new(
-                Expression::Identifier(Identifier::new("a".to_string())),
-                Expression::Integer(Integer::new("1".to_string())),
+                AttrPath::new(vec![Expression::Identifier(Identifier::new("a".to_string()))]),
+                Expression::Integer(Integer::new(1)),
             ))],
         );
         assert_eq!(rec_map.render().unwrap(), "rec { a = 1; }");
@@ -97,21 +115,66 @@ mod tests {
                 Expression::Identifier(Identifier::new("hello".to_string())),
             ])),
         );
-        assert_eq!(with.render().unwrap(), "with pkgs; [hello]");
+        assert_eq!(with.render().unwrap(), "with pkgs; [ hello ]");
     }
 
     #[test]
     fn test_bindings_rendering() {
         let inherit = BindingInherit::new(
             None,
-            Expression::Identifier(Identifier::new("attr".to_string())),
+            vec![Expression::Identifier(Identifier::new("attr".to_string()))],
         );
-        assert_eq!(inherit.render().unwrap(), "inherit attr");
+        assert_eq!(inherit.render().unwrap(), "inherit attr;");
+
+        let inherit_from = BindingInherit::new(
+            Some(Expression::Identifier(Identifier::new("src".to_string()))),
+            vec![
+                Expression::Identifier(Identifier::new("a".to_string())),
+                Expression::Identifier(Identifier::new("b".to_string())),
+            ],
+        );
+        assert_eq!(inherit_from.render().unwrap(), "inherit (src) a b;");
 
         let kv = BindingKeyValue::new(
-            Expression::Identifier(Identifier::new("name".to_string())),
+            AttrPath::new(vec![Expression::Identifier(Identifier::new("name".to_string()))]),
             Expression::Identifier(Identifier::new("value".to_string())),
         );
         assert_eq!(kv.render().unwrap(), "name = value;");
     }
+
+    #[test]
+    fn test_rendered_nodes_parse_back() {
+        let nodes = vec![
+            Expression::Function(Function::new(
+                FunctionHead::FunctionHeadDestructured(FunctionHeadDestructured::new(
+                    true,
+                    Some(Identifier::new("args".to_string())),
+                    vec![FunctionHeadDestructuredArgument::new(
+                        Identifier::new("a".to_string()),
+                        Some(Expression::Integer(Integer::new(42))),
+                    )],
+                )),
+                Expression::Identifier(Identifier::new("a".to_string())),
+            )),
+            Expression::AttrSet(AttrSet::new(
+                true,
+                vec![
+                    Expression::BindingKeyValue(BindingKeyValue::new(
+                        AttrPath::new(vec![Expression::Identifier(Identifier::new(
+                            "a".to_string(),
+                        ))]),
+                        Expression::Float(Float::new(10.0).unwrap()),
+                    )),
+                    Expression::BindingInherit(BindingInherit::new(
+                        Some(Expression::Identifier(Identifier::new("src".to_string()))),
+                        vec![Expression::Identifier(Identifier::new("b".to_string()))],
+                    )),
+                ],
+            )),
+        ];
+        for node in nodes {
+            let reparsed = parse(&node.render().unwrap()).unwrap();
+            assert_eq!(reparsed, vec![node]);
+        }
+    }
 }
@@ -0,0 +1,177 @@
+use nix_lens::parser::ast::json::{JSON_SCHEMA_VERSION, from_json, to_json, to_json_pretty};
+use nix_lens::parser::ast::Expression;
+use nix_lens::parser::parser::{parse, parse_recovering};
+use serde_json::Value;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(input: &str) {
+        let ast = parse(input).unwrap();
+        let back = from_json(&to_json(&ast).unwrap()).unwrap();
+        assert_eq!(back.len(), ast.len());
+        for (before, after) in ast.iter().zip(&back) {
+            assert_eq!(after.render().unwrap(), before.render().unwrap(), "{}", input);
+            assert_eq!(after.span().range(), before.span().range());
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip("{ a = x: x + 1; inherit (p) b; d = rec { c = -2.5; }.c or null; }");
+        round_trip("let f = { a, b ? 1, ... }@args: a; in f { a = [ ./x/${y}.nix <nixpkgs> ]; }");
+        round_trip("if !a.b ? c then ''\n  x ${y}\n'' else with z; assert q; throw \"no\"");
+        round_trip("~/a // /etc/b -> https://example.org");
+        round_trip("# doc\nx /* y */");
+    }
+
+    #[test]
+    fn test_document_shape() {
+        let ast = parse("a + 1").unwrap();
+        let value: Value = serde_json::from_str(&to_json_pretty(&ast).unwrap()).unwrap();
+        assert_eq!(value["version"], JSON_SCHEMA_VERSION);
+
+        let node = &value["expressions"][0];
+        assert_eq!(node["kind"], "BinaryOperation");
+        assert_eq!(node["operator"], "Addition");
+        assert_eq!(node["left"]["kind"], "Identifier");
+        assert_eq!(node["left"]["id"], "a");
+        assert_eq!(node["right"]["kind"], "Integer");
+        assert_eq!(node["right"]["value"], 1);
+        assert_eq!(node["span"]["start"]["offset"], 0);
+        assert_eq!(node["span"]["end"]["offset"], 5);
+        assert_eq!(node["span"]["end"]["column"], 6);
+        assert!(node.get("trivia").is_none());
+    }
+
+    #[test]
+    fn test_trivia() {
+        let ast = parse("# doc\nx").unwrap();
+        let value: Value = serde_json::from_str(&to_json(&ast).unwrap()).unwrap();
+        assert_eq!(value["expressions"][0]["trivia"]["leading"][0]["text"], "# doc");
+    }
+
+    #[test]
+    fn test_import_without_spans() {
+        let json = r#"{
+            "version": 1,
+            "expressions": [{
+                "kind": "AttrSet",
+                "recursive": false,
+                "bindings": [{
+                    "kind": "BindingKeyValue",
+                    "from": { "attributes": [{ "kind": "Identifier", "id": "a" }] },
+                    "to": {
+                        "kind": "UnaryOperation",
+                        "operator": "Not",
+                        "expression": { "kind": "Boolean", "value": true }
+                    }
+                }]
+            }]
+        }"#;
+        let ast = from_json(json).unwrap();
+        assert!(ast[0].span().is_detached());
+        assert_eq!(ast[0].render().unwrap(), "{ a = !true; }");
+    }
+
+    #[test]
+    fn test_nodes_built_in_code() {
+        let json = r#"{
+            "version": 1,
+            "expressions": [{
+                "kind": "Throw",
+                "message": { "kind": "LiteralString", "value": "no \"x\"" }
+            }]
+        }"#;
+        let ast = from_json(json).unwrap();
+        assert_eq!(ast[0].render().unwrap(), r#"throw "no \"x\"""#);
+        assert_eq!(from_json(&to_json(&ast).unwrap()).unwrap(), ast);
+    }
+
+    #[test]
+    fn test_error_nodes() {
+        let parsed = parse_recovering("{ a = ; }");
+        let back = from_json(&to_json(&parsed.expressions).unwrap()).unwrap();
+        let Expression::AttrSet(set) = &back[0] else { panic!("{:?}", back[0]) };
+        let Expression::BindingKeyValue(binding) = &set.bindings[0] else { panic!() };
+        let Expression::Error(error) = binding.to.as_ref() else { panic!() };
+        assert_eq!(error.diagnostic.code, parsed.diagnostics[0].code);
+        assert_eq!(error.diagnostic.span.range(), parsed.diagnostics[0].span.range());
+    }
+
+    #[test]
+    fn test_rejects_other_versions() {
+        let err = from_json(r#"{ "version": 2, "expressions": [] }"#).unwrap_err();
+        assert!(err.to_string().contains("unsupported schema version 2"), "{}", err);
+        assert!(from_json(r#"{ "expressions": [] }"#).is_err());
+    }
+
+    #[test]
+    fn test_rejects_unknown_nodes() {
+        let json = r#"{ "version": 1, "expressions": [{ "kind": "Goto" }] }"#;
+        assert!(from_json(json).is_err());
+        let json = r#"{ "version": 1, "expressions": [{
+            "kind": "UnaryOperation",
+            "operator": "Plus",
+            "expression": { "kind": "Null" }
+        }] }"#;
+        let err = from_json(json).unwrap_err();
+        assert!(err.to_string().contains("unknown operator `Plus`"), "{}", err);
+    }
+
+    #[test]
+    fn test_rejects_invalid_nodes() {
+        let document = |node: &str| format!(r#"{{ "version": 1, "expressions": [{}] }}"#, node);
+        for (node, message) in [
+            (
+                r#"{ "kind": "UnaryOperation", "operator": "Addition",
+                     "expression": { "kind": "Identifier", "id": "x" } }"#,
+                "unary operation",
+            ),
+            (
+                r#"{ "kind": "BinaryOperation", "operator": "Not",
+                     "left": { "kind": "Identifier", "id": "x" },
+                     "right": { "kind": "Identifier", "id": "y" } }"#,
+                "binary operation",
+            ),
+            (r#"{ "kind": "Identifier", "id": "a b" }"#, "`a b` is not an identifier"),
+            (r#"{ "kind": "Identifier", "id": "" }"#, "`` is not an identifier"),
+            (r#"{ "kind": "Identifier", "id": "let" }"#, "`let` is not an identifier"),
+            (
+                r#"{ "kind": "List", "elements": [{ "kind": "Identifier", "id": "1x" }] }"#,
+                "`1x` is not an identifier",
+            ),
+        ] {
+            let err = from_json(&document(node)).unwrap_err();
+            assert!(err.to_string().contains(message), "{}: {}", node, err);
+        }
+
+        let node = r#"{ "kind": "UnaryOperation", "operator": "Negate",
+                        "expression": { "kind": "Identifier", "id": "x'-y_" } }"#;
+        assert_eq!(from_json(&document(node)).unwrap()[0].render().unwrap(), "-x'-y_");
+    }
+
+    #[test]
+    fn test_number_lexemes() {
+        let document = |node: &str| format!(r#"{{ "version": 1, "expressions": [{}] }}"#, node);
+        let render = |node: &str| from_json(&document(node)).unwrap()[0].render().unwrap();
+        assert_eq!(render(r#"{ "kind": "Integer", "value": 5 }"#), "5");
+        assert_eq!(render(r#"{ "kind": "Integer", "value": 7, "lexeme": "007" }"#), "007");
+        assert_eq!(render(r#"{ "kind": "Float", "value": 2.5 }"#), "2.5");
+        assert_eq!(render(r#"{ "kind": "Float", "value": 10 }"#), "10.0");
+        assert_eq!(render(r#"{ "kind": "Float", "value": 250, "lexeme": "2.5e2" }"#), "2.5e2");
+
+        for node in [
+            r#"{ "kind": "Integer", "value": 5, "lexeme": "6" }"#,
+            r#"{ "kind": "Integer", "value": 5, "lexeme": "+5" }"#,
+            r#"{ "kind": "Integer", "value": 5, "lexeme": "x" }"#,
+            r#"{ "kind": "Float", "value": 2.5, "lexeme": "3.5" }"#,
+            r#"{ "kind": "Float", "value": 2, "lexeme": "2" }"#,
+            r#"{ "kind": "Float", "value": 2, "lexeme": "inf" }"#,
+        ] {
+            let err = from_json(&document(node)).unwrap_err();
+            assert!(err.to_string().contains("does not spell"), "{}: {}", node, err);
+        }
+    }
+}
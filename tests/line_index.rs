@@ -0,0 +1,62 @@
+use nix_lens::parser::ast::core::{LineCol, LineIndex};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "é" is two UTF-8 bytes and one UTF-16 unit, "😀" is four bytes and two units.
+    const TEXT: &str = "a = \"é\";\r\nb = \"😀x\";\n\nc";
+
+    #[test]
+    fn test_lines() {
+        let index = LineIndex::new(TEXT);
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_range(0), Some(0..10));
+        assert_eq!(&TEXT[index.line_range(1).unwrap()], "b = \"😀x\";");
+        assert_eq!(index.line_range(2), Some(24..24));
+        assert_eq!(index.line_range(4), None);
+    }
+
+    #[test]
+    fn test_offset_to_line_col() {
+        let index = LineIndex::new(TEXT);
+        let x = TEXT.find('x').unwrap();
+        assert_eq!(index.line_col(x), LineCol::new(1, 9));
+        assert_eq!(index.line_col_utf16(x), LineCol::new(1, 7));
+        assert_eq!(index.line_col(TEXT.len()), LineCol::new(3, 1));
+        assert_eq!(index.line_col(TEXT.len() + 10), LineCol::new(3, 1));
+
+        // Inside the emoji: moves back to its start.
+        assert_eq!(index.line_col(x - 2), LineCol::new(1, 5));
+    }
+
+    #[test]
+    fn test_line_col_to_offset() {
+        let index = LineIndex::new(TEXT);
+        let x = TEXT.find('x').unwrap();
+        assert_eq!(index.offset(LineCol::new(1, 9)), Some(x));
+        assert_eq!(index.offset_utf16(LineCol::new(1, 7)), Some(x));
+        assert_eq!(index.offset(LineCol::new(1, 6)), None);
+        assert_eq!(index.offset_utf16(LineCol::new(1, 6)), None);
+        assert_eq!(index.offset(LineCol::new(0, 10)), Some(10));
+        assert_eq!(index.offset(LineCol::new(0, 11)), None);
+        assert_eq!(index.offset_utf16(LineCol::new(0, 9)), Some(10));
+        assert_eq!(index.offset_utf16(LineCol::new(0, 10)), None);
+        assert_eq!(index.offset(LineCol::new(9, 0)), None);
+
+        assert_eq!(index.to_utf16(LineCol::new(0, 7)), Some(LineCol::new(0, 6)));
+        assert_eq!(index.to_utf8(LineCol::new(0, 6)), Some(LineCol::new(0, 7)));
+    }
+
+    #[test]
+    fn test_positions() {
+        let index = LineIndex::new(TEXT);
+        let x = TEXT.find('x').unwrap();
+        let position = index.position(x);
+        assert_eq!((position.line, position.column, position.offset), (2, 7, x));
+
+        let span = index.span(0..1);
+        assert_eq!(span.range(), 0..1);
+        assert_eq!((span.end.line, span.end.column), (1, 2));
+    }
+}
@@ -0,0 +1,915 @@
+use nix_lens::parser::ast::*;
+use nix_lens::parser::parser::{parse, parse_recovering};
+use nix_lens::format::{FormatOptions, format};
+
+fn parse_one(input: &str) -> Expression {
+    let mut expressions = parse(input).unwrap();
+    assert_eq!(expressions.len(), 1);
+    expressions.remove(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_string() {
+        let Expression::NixString(string) = parse_one(r#""hello""#) else {
+            panic!("expected a string");
+        };
+        assert_eq!(string.parts.len(), 1);
+        assert_eq!(string.value().unwrap(), "hello");
+        assert_eq!(string.render().unwrap(), r#""hello""#);
+
+        let Expression::NixString(empty) = parse_one(r#""""#) else {
+            panic!("expected a string");
+        };
+        assert!(empty.parts.is_empty());
+        assert_eq!(empty.value().unwrap(), "");
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let Expression::NixString(string) = parse_one(r#""a\n\"b\" \${c} $${d} $""#) else {
+            panic!("expected a string");
+        };
+        assert_eq!(string.parts.len(), 1);
+        assert_eq!(string.value().unwrap(), "a\n\"b\" ${c} $${d} $");
+        assert_eq!(string.render().unwrap(), r#""a\n\"b\" \${c} $${d} $""#);
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let Expression::NixString(string) = parse_one(r#""pre ${name} mid ${ "x${y}" }""#) else {
+            panic!("expected a string");
+        };
+        assert_eq!(string.parts.len(), 4);
+        assert!(string.value().is_none());
+
+        let Expression::PartRaw(pre) = &string.parts[0] else {
+            panic!("expected a raw part");
+        };
+        assert_eq!(pre.content, "pre ");
+        assert_eq!((pre.span.start.column, pre.span.end.column), (2, 6));
+
+        let Expression::PartInterpolation(name) = &string.parts[1] else {
+            panic!("expected an interpolation");
+        };
+        assert_eq!((name.span.start.column, name.span.end.column), (6, 13));
+        assert!(matches!(*name.expression, Expression::Identifier(ref id) if id.id == "name"));
+
+        let Expression::PartInterpolation(nested) = &string.parts[3] else {
+            panic!("expected an interpolation");
+        };
+        assert!(matches!(*nested.expression, Expression::NixString(_)));
+
+        assert_eq!(string.render().unwrap(), r#""pre ${name} mid ${"x${y}"}""#);
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        assert!(parse(r#""abc"#).is_err());
+    }
+
+    #[test]
+    fn test_indented_string() {
+        let source = "''\n    echo hello\n      echo ${name}\n  ''";
+        let Expression::IndentedString(string) = parse_one(source) else {
+            panic!("expected an indented string");
+        };
+        assert_eq!(string.parts.len(), 3);
+        assert_eq!(string.raw().unwrap(), &source[2..source.len() - 2]);
+        assert_eq!(string.render().unwrap(), source);
+
+        let cooked = string.cooked();
+        assert_eq!(cooked.len(), 3);
+        assert!(matches!(&cooked[0], CookedPart::Text(text) if text == "echo hello\n  echo "));
+        assert!(matches!(cooked[1], CookedPart::Interpolation(Expression::Identifier(_))));
+        assert!(matches!(&cooked[2], CookedPart::Text(text) if text == "\n"));
+    }
+
+    #[test]
+    fn test_indented_string_escapes() {
+        let source = "''\n  a ''' b ''${c} $${d}\n  ''\\tx\n''";
+        let Expression::IndentedString(string) = parse_one(source) else {
+            panic!("expected an indented string");
+        };
+        assert_eq!(string.parts.len(), 1);
+        assert_eq!(string.value().unwrap(), "a '' b ${c} $${d}\n\tx\n");
+        assert_eq!(string.render().unwrap(), source);
+    }
+
+    #[test]
+    fn test_indented_string_escape_is_not_indentation() {
+        // An escape at the start of a line ends its indentation, so the
+        // minimum indentation here is zero and nothing is stripped.
+        let Expression::IndentedString(string) = parse_one("''\n    a\n''\\tb\n''") else {
+            panic!("expected an indented string");
+        };
+        assert_eq!(string.value().unwrap(), "    a\n\tb\n");
+
+        let Expression::IndentedString(inline) = parse_one("''  x ''") else {
+            panic!("expected an indented string");
+        };
+        assert_eq!(inline.value().unwrap(), "x ");
+    }
+
+    fn render(input: &str) -> String {
+        parse_one(input).render().unwrap()
+    }
+
+    /// Checks that `input` groups like the parenthesized `grouped`, and that
+    /// rendering drops the redundant parentheses again.
+    fn assert_grouping(input: &str, grouped: &str) {
+        assert_eq!(parse_one(input), parse_one(grouped), "{}", input);
+        assert_eq!(render(grouped), input);
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        assert_grouping("1 + 2 * 3", "1 + (2 * 3)");
+        assert_grouping("1 * 2 + 3", "(1 * 2) + 3");
+        assert_grouping("a ++ b * c", "(a ++ b) * c");
+        assert_grouping("a || b && c == d", "a || (b && (c == d))");
+        assert_grouping("a == b < c", "a == (b < c)");
+        assert_grouping("f x + g y", "(f x) + (g y)");
+        assert_eq!(render("(1 + 2) * 3"), "(1 + 2) * 3");
+        assert_eq!(render("((a))"), "a");
+    }
+
+    #[test]
+    fn test_operator_associativity() {
+        assert_grouping("1 - 2 - 3", "(1 - 2) - 3");
+        assert_grouping("a ++ b ++ c", "a ++ (b ++ c)");
+        assert_grouping("a && b && c", "(a && b) && c");
+        assert_eq!(render("1 - (2 - 3)"), "1 - (2 - 3)");
+        assert_eq!(render("(a ++ b) ++ c"), "(a ++ b) ++ c");
+        assert_eq!(render("(a < b) < c"), "(a < b) < c");
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        for source in [
+            "(x: x + 1) 2",
+            "f (x: x) (g y)",
+            "(f a) b",
+            "(f a).b",
+            "f a.b or c d",
+            "a.b or (c + 1)",
+            "{ a = x: x; }.a",
+            "(if a then b else c) + (let x = 1; in x)",
+            "x: y: x + y",
+            "x: (y: y) x",
+            "with a; assert b; c",
+            "-(x: x)",
+            "(with a; b) ? c",
+        ] {
+            assert_eq!(render(source), source);
+            assert_eq!(parse_one(&render(source)), parse_one(source));
+        }
+
+        let negative = FunctionApplication::new(
+            Expression::Identifier(Identifier::new("f".to_string())),
+            vec![Expression::Integer(Integer::new(-1))],
+        );
+        assert_eq!(negative.render().unwrap(), "f (-1)");
+        let throw = Throw::new(parse_one("\"a\" + b"));
+        assert_eq!(throw.render().unwrap(), "throw (\"a\" + b)");
+    }
+
+    #[test]
+    fn test_non_associative_operators() {
+        assert!(parse("a < b < c").is_err());
+        assert!(parse("a == b != c").is_err());
+        assert!(parse("a < b + 1 >= c").is_err());
+        assert!(parse("(a < b) < c").is_ok());
+        assert!(parse("a < b == c > d").is_ok());
+    }
+
+    #[test]
+    fn test_binary_operation_span() {
+        let Expression::BinaryOperation(operation) = parse_one("a + b * c") else {
+            panic!("expected a binary operation");
+        };
+        assert_eq!((operation.span.start.column, operation.span.end.column), (1, 10));
+        assert_eq!((operation.right.span().start.column, operation.right.span().end.column), (5, 10));
+    }
+
+    #[test]
+    fn test_trailing_input_is_rejected() {
+        assert!(parse("1 +").is_err());
+        assert!(parse("1 )").is_err());
+    }
+
+    #[test]
+    fn test_update_and_implication_operators() {
+        assert_grouping("a // b // c", "a // (b // c)");
+        assert_grouping("a -> b -> c", "a -> (b -> c)");
+        assert_grouping("a || b -> c", "(a || b) -> c");
+        assert_grouping("a // b == c", "(a // b) == c");
+        assert_eq!(render("a / b"), "a / b");
+        assert_eq!(render("a - b"), "a - b");
+    }
+
+    #[test]
+    fn test_legacy_operator_tokens() {
+        let error = parse("a := b").unwrap_err();
+        assert_eq!(error.help.as_deref(), Some("write `//` for attribute set update"));
+
+        let error = parse("a => b").unwrap_err();
+        assert_eq!(error.help.as_deref(), Some("write `->` for logical implication"));
+    }
+
+    #[test]
+    fn test_unary_operators() {
+        assert_eq!(render("!x"), "!x");
+        assert_eq!(render("-x"), "-x");
+        assert_eq!(render("- -1"), "--1");
+        assert_grouping("-a * b", "(-a) * b");
+        assert_grouping("!a + b", "!(a + b)");
+        assert_grouping("!a == b", "(!a) == b");
+        assert_grouping("!a && !b", "(!a) && (!b)");
+        assert_grouping("a - -b", "a - (-b)");
+        assert_grouping("-f x", "-(f x)");
+        assert_eq!(render("(!a) + b"), "(!a) + b");
+        assert_eq!(render("-(a ? b)"), "-(a ? b)");
+
+        let Expression::UnaryOperation(operation) = parse_one("!x") else {
+            panic!("expected a unary operation");
+        };
+        assert!(matches!(operation.operator, Operator::Not(_)));
+        assert_eq!((operation.span.start.column, operation.span.end.column), (1, 3));
+
+        let Expression::BinaryOperation(operation) = parse_one("-2 * 3") else {
+            panic!("expected a binary operation");
+        };
+        assert!(matches!(*operation.left, Expression::UnaryOperation(_)));
+    }
+
+    #[test]
+    fn test_unary_operators_round_trip() {
+        for source in ["!x", "-x", "!(a || b)", "-(1 + 2)", "!!x", "- -x", "!a -> b"] {
+            let rendered = render(source);
+            assert_eq!(render(&rendered), rendered, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_simple_lambda() {
+        let Expression::Function(function) = parse_one("x: x + 1") else {
+            panic!("expected a function");
+        };
+        let FunctionHead::FunctionHeadSimple(head) = &function.head else {
+            panic!("expected a simple head");
+        };
+        assert_eq!(head.identifier.id, "x");
+        assert!(matches!(*function.body, Expression::BinaryOperation(_)));
+        assert_eq!(render("x: y: x"), "x: y: x");
+    }
+
+    #[test]
+    fn test_destructured_lambda() {
+        let Expression::Function(function) = parse_one("{ a, b ? 1, ... }: a") else {
+            panic!("expected a function");
+        };
+        let FunctionHead::FunctionHeadDestructured(head) = &function.head else {
+            panic!("expected a destructured head");
+        };
+        assert!(head.ellipsis);
+        assert!(head.identifier.is_none());
+        assert_eq!(head.arguments.len(), 2);
+        assert_eq!(head.arguments[0].identifier.id, "a");
+        assert_eq!(head.arguments[0].identifier.span.range(), 2..3);
+        assert_eq!(head.arguments[0].span.range(), 2..3);
+        assert!(head.arguments[0].default.is_none());
+        assert_eq!(head.arguments[1].identifier.span.range(), 5..6);
+        assert_eq!(head.arguments[1].span.range(), 5..10);
+        assert!(matches!(head.arguments[1].default, Some(Expression::Integer(_))));
+
+        assert_eq!(render("{ }: 1"), "{ }: 1");
+        assert_eq!(render("{ a, }: a"), "{ a }: a");
+        assert_eq!(render("{...}: 1"), "{ ... }: 1");
+        assert!(parse("{ ..., a }: a").is_err());
+    }
+
+    #[test]
+    fn test_lambda_at_bindings() {
+        let Expression::Function(function) = parse_one("args@{ a, ... }: args") else {
+            panic!("expected a function");
+        };
+        let FunctionHead::FunctionHeadDestructured(head) = &function.head else {
+            panic!("expected a destructured head");
+        };
+        assert_eq!(head.identifier.as_ref().unwrap().id, "args");
+        assert!(head.identifier_before);
+
+        assert_eq!(render("args@{ a, ... }: args"), "args@{ a, ... }: args");
+        assert_eq!(render("{ a, ... }@args: args"), "{ a, ... }@args: args");
+        assert_eq!(render("{ }@args: args"), "{ }@args: args");
+    }
+
+    #[test]
+    fn test_attrset() {
+        let Expression::AttrSet(set) = parse_one("{ a = 1; b.c = \"x\"; }") else {
+            panic!("expected an attribute set");
+        };
+        assert!(!set.recursive);
+        assert_eq!(set.bindings.len(), 2);
+        let Expression::BindingKeyValue(binding) = &set.bindings[1] else {
+            panic!("expected a key-value binding");
+        };
+        assert_eq!(binding.from_.attributes.len(), 2);
+        assert_eq!((binding.span.start.column, binding.span.end.column), (10, 20));
+        assert_eq!((binding.from_.span.start.column, binding.from_.span.end.column), (10, 13));
+
+        assert_eq!(render("{ }"), "{ }");
+        assert_eq!(render("{a=1;b=2;}"), "{ a = 1; b = 2; }");
+    }
+
+    #[test]
+    fn test_rec_attrset() {
+        let Expression::AttrSet(set) = parse_one("rec { a = 1; b = a; }") else {
+            panic!("expected an attribute set");
+        };
+        assert!(set.recursive);
+        assert_eq!(render("rec {}"), "rec { }");
+        assert!(matches!(parse_one("record { }"), Expression::FunctionApplication(_)));
+    }
+
+    #[test]
+    fn test_attribute_keys() {
+        assert_eq!(render("{ \"quoted key\" = 1; }"), "{ \"quoted key\" = 1; }");
+        assert_eq!(render("{ ${dyn} = 1; }"), "{ ${dyn} = 1; }");
+        assert_eq!(render("{ a.\"b\".${c} = 1; }"), "{ a.\"b\".${c} = 1; }");
+
+        let Expression::AttrSet(set) = parse_one("{ a.\"b\".${c} = 1; }") else {
+            panic!("expected an attribute set");
+        };
+        let Expression::BindingKeyValue(binding) = &set.bindings[0] else {
+            panic!("expected a key-value binding");
+        };
+        assert!(matches!(binding.from_.attributes[0], Expression::Identifier(_)));
+        assert!(matches!(binding.from_.attributes[1], Expression::NixString(_)));
+        assert!(matches!(binding.from_.attributes[2], Expression::PartInterpolation(_)));
+    }
+
+    #[test]
+    fn test_inherit() {
+        assert_eq!(render("{ inherit a b; }"), "{ inherit a b; }");
+        assert_eq!(render("{ inherit (src) a \"b\"; }"), "{ inherit (src) a \"b\"; }");
+        assert_eq!(render("{ inheritance = 1; }"), "{ inheritance = 1; }");
+
+        let Expression::AttrSet(set) = parse_one("{ inherit (a // b) a; }") else {
+            panic!("expected an attribute set");
+        };
+        let Expression::BindingInherit(inherit) = &set.bindings[0] else {
+            panic!("expected an inherit binding");
+        };
+        assert!(inherit.from_.is_some());
+        assert_eq!(inherit.attributes.len(), 1);
+    }
+
+    #[test]
+    fn test_let_in() {
+        let Expression::LetIn(let_in) = parse_one("let a = 1; inherit (x) b; in a + b") else {
+            panic!("expected a let expression");
+        };
+        assert_eq!(let_in.bindings.len(), 2);
+        assert!(matches!(*let_in.target, Expression::BinaryOperation(_)));
+        assert_eq!((let_in.span.start.column, let_in.span.end.column), (1, 35));
+        assert_eq!(render("let in x"), "let in x");
+        assert_eq!(render("let a = 1; in a"), "let a = 1; in a");
+        assert!(matches!(parse_one("letter"), Expression::Identifier(_)));
+    }
+
+    #[test]
+    fn test_with_and_assert() {
+        let Expression::With(with) = parse_one("with pkgs; hello") else {
+            panic!("expected a with expression");
+        };
+        assert!(matches!(*with.scope, Expression::Identifier(_)));
+        assert_eq!(render("with a; with b; c"), "with a; with b; c");
+
+        let Expression::Assert(assert) = parse_one("assert a == b; a") else {
+            panic!("expected an assert expression");
+        };
+        assert!(matches!(*assert.condition, Expression::BinaryOperation(_)));
+        assert_eq!(render("assert x; y"), "assert x; y");
+    }
+
+    #[test]
+    fn test_if_then_else() {
+        let Expression::IfThenElse(branch) = parse_one("if a then b else if c then d else e") else {
+            panic!("expected an if expression");
+        };
+        assert!(matches!(*branch.else_, Expression::IfThenElse(_)));
+        assert_eq!(render("if a then b else c"), "if a then b else c");
+        assert!(parse("1 + if a then b else c").is_err());
+        assert!(parse("if a then b").is_err());
+    }
+
+    #[test]
+    fn test_list() {
+        let Expression::List(list) = parse_one("[ 1 \"two\" (f x) [ ] ]") else {
+            panic!("expected a list");
+        };
+        assert_eq!(list.elements.len(), 4);
+        assert!(matches!(list.elements[2], Expression::FunctionApplication(_)));
+        assert_eq!((list.span.start.column, list.span.end.column), (1, 22));
+
+        let Expression::List(list) = parse_one("[ f x ]") else {
+            panic!("expected a list");
+        };
+        assert_eq!(list.elements.len(), 2);
+    }
+
+    #[test]
+    fn test_select() {
+        let Expression::PropertyAccess(access) = parse_one("pkgs.lib.foo") else {
+            panic!("expected a property access");
+        };
+        assert!(matches!(*access.expression, Expression::Identifier(_)));
+        assert_eq!(access.attribute_path.attributes.len(), 2);
+        assert!(access.default.is_none());
+        assert_eq!(render("pkgs.lib.foo"), "pkgs.lib.foo");
+        assert_eq!(render("a.\"b c\".${d}"), "a.\"b c\".${d}");
+        assert_eq!(render("{ a = 1; }.a"), "{ a = 1; }.a");
+    }
+
+    #[test]
+    fn test_select_or_default() {
+        let Expression::PropertyAccess(access) = parse_one("config.services.foo.enable or false") else {
+            panic!("expected a property access");
+        };
+        assert!(matches!(access.default.as_deref(), Some(Expression::Boolean(_))));
+        assert_eq!(render("a.b or null"), "a.b or null");
+        assert_eq!(render("a.b or c.d or e"), "a.b or c.d or e");
+        assert_eq!(render("f a.b or c d"), "f a.b or c d");
+    }
+
+    #[test]
+    fn test_select_binds_tighter_than_application() {
+        let Expression::FunctionApplication(application) = parse_one("f x.y z") else {
+            panic!("expected a function application");
+        };
+        assert_eq!(application.arguments.len(), 2);
+        assert!(matches!(application.arguments[0], Expression::PropertyAccess(_)));
+    }
+
+    #[test]
+    fn test_has_attribute() {
+        let Expression::HasAttribute(has) = parse_one("e ? a.b.c") else {
+            panic!("expected a has-attribute test");
+        };
+        assert_eq!(has.attribute_path.attributes.len(), 3);
+        assert_eq!((has.span.start.column, has.span.end.column), (1, 10));
+        assert_grouping("x ? \"a\" && y", "(x ? \"a\") && y");
+        assert_grouping("!x ? a", "!(x ? a)");
+        assert_grouping("a ++ b ? c", "a ++ (b ? c)");
+        assert_eq!(render("(a ++ b) ? c"), "(a ++ b) ? c");
+        assert_eq!(render("(a ? b) ? c"), "(a ? b) ? c");
+        assert!(parse("a ? b ? c").is_err());
+        assert!(parse("(a ? b) == true").is_ok());
+    }
+
+    #[test]
+    fn test_paths() {
+        for (source, kind) in [
+            ("./a/b.nix", PathKind::Relative),
+            ("../a", PathKind::Relative),
+            ("a/b", PathKind::Relative),
+            ("/etc/x", PathKind::Absolute),
+            ("~/x", PathKind::Home),
+        ] {
+            let Expression::Path(path) = parse_one(source) else {
+                panic!("expected a path for {}", source);
+            };
+            assert_eq!(path.kind, kind, "{}", source);
+            assert_eq!(path.parts.len(), 1);
+            assert_eq!(path.render().unwrap(), source);
+        }
+        assert!(parse("./a/").is_err());
+        assert!(matches!(parse_one("a / b"), Expression::BinaryOperation(_)));
+        assert!(matches!(parse_one("a // b"), Expression::BinaryOperation(_)));
+    }
+
+    #[test]
+    fn test_interpolated_paths() {
+        let Expression::Path(path) = parse_one("./${name}.nix") else {
+            panic!("expected a path");
+        };
+        assert_eq!(path.parts.len(), 3);
+        assert!(matches!(path.parts[0], Expression::PartRaw(ref raw) if raw.content == "./"));
+        assert!(matches!(path.parts[1], Expression::PartInterpolation(_)));
+        assert!(matches!(path.parts[2], Expression::PartRaw(ref raw) if raw.content == ".nix"));
+        assert_eq!(render("./${a}/b${c}"), "./${a}/b${c}");
+        assert_eq!(render("import ./${name}.nix"), "import ./${name}.nix");
+    }
+
+    #[test]
+    fn test_search_paths() {
+        let Expression::SearchNixPath(path) = parse_one("<nixpkgs/lib>") else {
+            panic!("expected a search path");
+        };
+        assert_eq!(path.path, "nixpkgs/lib");
+        assert_eq!(render("import <nixpkgs> { }"), "import <nixpkgs> { }");
+        assert!(matches!(parse_one("a < b"), Expression::BinaryOperation(_)));
+    }
+
+    #[test]
+    fn test_uris() {
+        let Expression::Uri(uri) = parse_one("https://example.com/a?b=c") else {
+            panic!("expected a URI");
+        };
+        assert_eq!(uri.uri, "https://example.com/a?b=c");
+        assert!(matches!(parse_one("x:x"), Expression::Uri(_)));
+        assert!(matches!(parse_one("x: x"), Expression::Function(_)));
+    }
+
+    #[test]
+    fn test_booleans_and_null() {
+        assert!(matches!(parse_one("true"), Expression::Boolean(ref x) if x.value));
+        assert!(matches!(parse_one("false"), Expression::Boolean(ref x) if !x.value));
+        assert!(matches!(parse_one("null"), Expression::Null(_)));
+        assert!(matches!(parse_one("nullable"), Expression::Identifier(_)));
+        assert_eq!(render("{ a = null; }"), "{ a = null; }");
+    }
+
+    #[test]
+    fn test_integers() {
+        let Expression::Integer(integer) = parse_one("007") else {
+            panic!("expected an integer");
+        };
+        assert_eq!(integer.value, 7);
+        assert_eq!(integer.render(), "007");
+
+        let Expression::Integer(max) = parse_one("9223372036854775807") else {
+            panic!("expected an integer");
+        };
+        assert_eq!(max.value, i64::MAX);
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let error = parse("1 + 9223372036854775808").unwrap_err();
+        assert!(error.message.contains("does not fit"), "{}", error.message);
+        assert_eq!(error.span.start.column, 5);
+    }
+
+    #[test]
+    fn test_floats() {
+        for (source, value) in [
+            ("1.5", 1.5),
+            (".5", 0.5),
+            ("0.25", 0.25),
+            ("1.", 1.0),
+            ("1e10", 1e10),
+            ("2.5E-3", 2.5e-3),
+            ("1.5e+2", 150.0),
+        ] {
+            let Expression::Float(float) = parse_one(source) else {
+                panic!("expected a float for {}", source);
+            };
+            assert_eq!(float.value, value, "{}", source);
+            assert_eq!(float.render(), source);
+        }
+        assert!(matches!(parse_one("10"), Expression::Integer(_)));
+        assert!(parse("1e999").is_err());
+    }
+
+    #[test]
+    fn test_comments_round_trip() {
+        assert_eq!(render("1 /* one */ + # two\n 2"), "1 /* one */ + # two\n2");
+        assert_eq!(render("[ 1 /* a */ 2 ]"), "[ 1 /* a */ 2 ]");
+        assert_eq!(render("\"# not a comment\""), "\"# not a comment\"");
+        // `#` is not a path character, so this starts a comment as in Nix.
+        assert_eq!(render("./a/b#c"), "./a/b #c\n");
+    }
+
+    #[test]
+    fn test_comment_attachment() {
+        let source = "# header\n{\n  # leading\n  a = 1; # trailing\n  b = 2;\n}";
+        let root = parse_one(source);
+        assert_eq!(root.trivia().leading[0].text, "# header");
+        let Expression::AttrSet(set) = &root else {
+            panic!("expected an attribute set");
+        };
+        let a = set.bindings[0].trivia();
+        assert_eq!(a.leading[0].text, "# leading");
+        assert_eq!(a.leading[0].span.start.line, 3);
+        assert_eq!(a.trailing[0].text, "# trailing");
+        assert!(set.bindings[1].trivia().is_empty());
+        assert_eq!(root.render().unwrap(), source);
+    }
+
+    #[test]
+    fn test_comments_stay_after_tokens() {
+        let Expression::Assert(assert) = parse_one("assert a; # c\nb") else {
+            panic!("expected an assert");
+        };
+        assert_eq!(assert.body.trivia().leading[0].text, "# c");
+        assert_eq!(assert.render().unwrap(), "assert a; # c\nb");
+
+        assert_eq!(render("{ inherit (x) # c\n a b; }"), "{\n  inherit (x) # c\n  a b;\n}");
+        assert_eq!(render("[ (a b) # c\n ]"), "[\n  (a b) # c\n]");
+        assert_eq!(render("let a = 1; in # c\n a"), "let a = 1; in # c\na");
+        assert_eq!(render("{ a = { b = 1; # c\n }; }"), "{\n  a = {\n    b = 1; # c\n  };\n}");
+        // Indenting the second line would change the string.
+        assert_eq!(render("[ \"a\nb\" # c\n ]"), "[\n  \"a\nb\" # c\n]");
+    }
+
+    #[test]
+    fn test_comments_on_formals() {
+        let source =
+            "{ stdenv\n, fetchurl\n# Enable the thing\n, enableFoo ? false\n, bar # the bar\n}:\nx";
+        let Expression::Function(function) = parse_one(source) else {
+            panic!("expected a function");
+        };
+        let FunctionHead::FunctionHeadDestructured(head) = &function.head else {
+            panic!("expected a destructured head");
+        };
+        let [stdenv, fetchurl, enable_foo, bar] = &head.arguments[..] else {
+            panic!("expected four formals");
+        };
+        assert!(stdenv.trivia.is_empty() && fetchurl.trivia.is_empty());
+        assert_eq!(enable_foo.trivia.leading[0].text, "# Enable the thing");
+        assert!(enable_foo.default.as_ref().unwrap().trivia().is_empty());
+        assert_eq!(bar.trivia.trailing[0].text, "# the bar");
+        assert!(function.body.trivia().is_empty());
+        assert_eq!(
+            function.render().unwrap(),
+            "{ stdenv, fetchurl, # Enable the thing\nenableFoo ? false, bar # the bar\n }: x"
+        );
+
+        assert_eq!(render("{ a, # c\n b }: a"), "{ a # c\n, b }: a");
+        assert_eq!(render("{ a ? /* c */ 1 }: a"), "{ a ? /* c */ 1 }: a");
+    }
+
+    #[test]
+    fn test_comments_inside_nodes() {
+        let Expression::AttrSet(set) = parse_one("{ /* nothing here */ }") else {
+            panic!("expected an attribute set");
+        };
+        assert_eq!(set.trivia.inner[0].text, "/* nothing here */");
+        assert!(set.trivia.trailing.is_empty());
+        assert_eq!(set.render().unwrap(), "{ /* nothing here */ }");
+        assert_eq!(render("[ # c\n]"), "[\n  # c\n]");
+        assert_eq!(render("rec { # c\n} # d"), "rec {\n  # c\n} # d\n");
+
+        let rendered = render("\"${ x # why\n}\"");
+        assert_eq!(rendered, "\"${x # why\n}\"");
+        assert!(parse(&rendered).is_ok());
+    }
+
+    #[test]
+    fn test_recover_missing_value() {
+        let parsed = parse_recovering("{ a = ; b = 2; }");
+        assert_eq!(parsed.diagnostics.len(), 1);
+        assert_eq!(parsed.diagnostics[0].code, "NL0001");
+        assert_eq!(parsed.diagnostics[0].span.start.column, 7);
+        let Expression::AttrSet(set) = &parsed.expressions[0] else {
+            panic!("expected an attribute set");
+        };
+        let Expression::BindingKeyValue(a) = &set.bindings[0] else {
+            panic!("expected a binding");
+        };
+        let Expression::Error(error) = a.to.as_ref() else {
+            panic!("expected an error");
+        };
+        assert_eq!(a.to.span(), parsed.diagnostics[0].span);
+        assert_eq!(error.span(), &error.diagnostic.span);
+        assert_eq!(set.bindings[1].render().unwrap(), "b = 2;");
+        assert!(parse("{ a = ; b = 2; }").is_err());
+    }
+
+    #[test]
+    fn test_recover_bad_bindings_and_elements() {
+        let parsed = parse_recovering("let x y; z = 1; in [ 1 ) z ]");
+        assert_eq!(parsed.diagnostics.len(), 2);
+        assert!(parsed.diagnostics[0].message.contains("expected a binding"));
+        assert!(parsed.diagnostics[1].message.contains("unexpected `)` in list"));
+        let Expression::LetIn(let_in) = &parsed.expressions[0] else {
+            panic!("expected let");
+        };
+        assert!(matches!(let_in.bindings[0], Expression::Error(_)));
+        assert_eq!(let_in.bindings[1].render().unwrap(), "z = 1;");
+        let Expression::List(list) = &*let_in.target else {
+            panic!("expected a list");
+        };
+        assert_eq!(list.elements.len(), 3);
+    }
+
+    #[test]
+    fn test_recover_unclosed_and_trailing() {
+        let parsed = parse_recovering("{ a = 1;\n  b = ");
+        assert_eq!(parsed.diagnostics.len(), 2);
+        let Expression::AttrSet(set) = &parsed.expressions[0] else {
+            panic!("expected an attribute set");
+        };
+        assert_eq!(set.bindings[0].render().unwrap(), "a = 1;");
+
+        let parsed = parse_recovering("1 + 2 )");
+        assert_eq!(parsed.expressions.len(), 2);
+        assert_eq!(parsed.diagnostics[0].message, "unexpected `)`");
+        assert_eq!(parsed.diagnostics[0].span.start.column, 7);
+
+        let parsed = parse_recovering("[ 99999999999999999999 2 ]");
+        assert!(parsed.diagnostics[0].message.contains("does not fit"));
+
+        assert_eq!(parse_recovering("").diagnostics.len(), 1);
+        assert!(parse_recovering("{ a = 1; }").diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_nesting_depth() {
+        // Spawned threads get a 2MB stack, which deep input used to overflow.
+        std::thread::spawn(|| {
+            let nested = |open: &str, close: &str, depth| {
+                format!("{}x{}", open.repeat(depth), close.repeat(depth))
+            };
+            // Whatever the check lets through can also be cloned, compared,
+            // rendered and formatted without running out of stack.
+            for input in [
+                nested("(", ")", 80),
+                nested("{ a = ", "; }", 32),
+                nested("\"${", "}\"", 32),
+                nested("f: ", "", 64),
+                nested("let a = 1; in ", "", 64),
+                nested("if x then x else ", "", 70),
+                nested("x ++ ", "", 200),
+                nested("x + ", "", 300),
+                nested("x - ", "", 70),
+                nested("[ x ] ++ ", "", 300),
+                format!("[ {}]", "a.b or c ".repeat(100)),
+                format!("[ {}]", "(x + x) ".repeat(1000)),
+                format!("{{ {}}}", "a = x + x; ".repeat(1000)),
+            ] {
+                let parsed = parse(&input).unwrap();
+                assert!(parsed.clone() == parsed);
+                parsed[0].render().unwrap();
+                format(&input, &FormatOptions::default()).unwrap();
+            }
+
+            for input in [
+                nested("(", ")", 1000),
+                nested("[ ", " ]", 1000),
+                nested("{ a = ", "; }", 1000),
+                nested("\"${", "}\"", 1000),
+                nested("if x then x else ", "", 1000),
+                nested("x: ", "", 1000),
+                nested("-", "", 1000),
+                nested("x ++ ", "", 1000),
+            ] {
+                let parsed = parse_recovering(&input);
+                assert_eq!(parsed.diagnostics.len(), 1);
+                assert_eq!(parsed.diagnostics[0].code, "NL0011");
+                assert!(matches!(parsed.expressions[..], [Expression::Error(_)]));
+                assert!(nix_lens::parser::cst::parse_cst(&input).is_err());
+            }
+            let error = parse(&nested("(", ")", 81)).unwrap_err();
+            assert_eq!(error.span.start.offset, 80);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_diagnostic_rendering() {
+        let source = "{\n  a = ;\n  b = 2;\n}";
+        let diagnostic = parse(source).unwrap_err();
+        assert_eq!(
+            diagnostic.render(source, "default.nix"),
+            "error[NL0001]: expected an expression\n \
+             --> default.nix:2:7\n  \
+             |\n\
+             2 |   a = ;\n  \
+             |       ^ expected an expression here\n"
+        );
+
+        let source = "a < b < c";
+        let diagnostic = parse(source).unwrap_err();
+        assert_eq!(diagnostic.code, "NL0008");
+        assert_eq!(
+            diagnostic.render(source, "x.nix"),
+            "error[NL0008]: operator `<` cannot be chained with `<`\n \
+             --> x.nix:1:7\n  \
+             |\n\
+             1 | a < b < c\n  \
+             |       ^\n  \
+             |   - first `<` here\n  \
+             |\n  \
+             = help: add parentheses\n"
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "error[NL0008]: operator `<` cannot be chained with `<` at line 1, column 7; add parentheses"
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_rendering_unclosed() {
+        let source = "[ 1\n  2";
+        let diagnostic = parse(source).unwrap_err();
+        assert_eq!(
+            diagnostic.render(source, "list.nix"),
+            "error[NL0002]: unexpected end of input\n \
+             --> list.nix:2:4\n  \
+             |\n\
+             1 | [ 1\n  \
+             | - unclosed here\n\
+             2 |   2\n  \
+             |    ^ expected a closing bracket\n"
+        );
+    }
+
+    #[test]
+    fn test_span_offsets() {
+        let source = "let\n  s = \"ü\"; t = s;\nin t";
+        let Expression::LetIn(let_in) = parse_one(source) else {
+            panic!("expected let");
+        };
+        let binding = let_in.bindings[1].span();
+        assert_eq!(&source[binding.range()], "t = s;");
+        assert_eq!((binding.start.line, binding.start.column), (2, 12));
+        assert_eq!((binding.end.line, binding.end.column), (2, 18));
+        assert_eq!(binding.start.offset, 16);
+        let target = let_in.target.span();
+        assert_eq!(&source[target.range()], "t");
+        assert!(target.contains_offset(source.len()));
+
+        let index = LineIndex::new(source);
+        let position = index.position(target.start.offset);
+        assert_eq!((position.line, position.column), (target.start.line, target.start.column));
+    }
+
+    #[test]
+    fn test_built_nodes_are_detached() {
+        assert!(Integer::new(1).span.is_detached());
+        assert!(!parse_one("1").span().is_detached());
+    }
+
+    #[test]
+    fn test_node_at() {
+        let source = "{ a = f x.y; b = [ 1 \"${c}\" ]; }";
+        let ast = parse(source).unwrap();
+
+        let found = node_at(&ast, source.find('y').unwrap()).unwrap();
+        assert!(matches!(found.node, Expression::Identifier(i) if i.id == "y"));
+        let kinds: Vec<String> = found
+            .iter()
+            .map(|e| format!("{:?}", e).split('(').next().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            kinds,
+            ["Identifier", "PropertyAccess", "FunctionApplication", "BindingKeyValue", "AttrSet"]
+        );
+        assert!(matches!(found.parent(), Some(Expression::PropertyAccess(_))));
+
+        // Right after `c`, inside the interpolation.
+        let found = node_at(&ast, source.find("c}").unwrap() + 1).unwrap();
+        assert!(matches!(found.node, Expression::Identifier(i) if i.id == "c"));
+        assert!(matches!(found.parent(), Some(Expression::PartInterpolation(_))));
+
+        // Right after `;`, the binding that just ended; on other whitespace,
+        // the attribute set itself.
+        let found = node_at(&ast, source.find(" b").unwrap()).unwrap();
+        assert!(matches!(found.node, Expression::BindingKeyValue(_)));
+        let found = node_at(&ast, 1).unwrap();
+        assert!(matches!(found.node, Expression::AttrSet(_)));
+        assert!(found.ancestors.is_empty());
+
+        assert!(node_at(&ast, source.len() + 1).is_none());
+        assert!(node_at(&ast, 1).unwrap().formal.is_none());
+    }
+
+    #[test]
+    fn test_node_at_formals() {
+        let source = "{ enableFoo ? false }: enableFoo";
+        let ast = parse(source).unwrap();
+
+        let found = node_at(&ast, source.find("Foo").unwrap()).unwrap();
+        assert!(matches!(found.node, Expression::Function(_)));
+        let formal = found.formal.unwrap();
+        assert_eq!(formal.identifier.id, "enableFoo");
+        assert_eq!(formal.identifier.span.range(), 2..11);
+
+        let found = node_at(&ast, source.find("false").unwrap()).unwrap();
+        assert!(matches!(found.node, Expression::Boolean(_)));
+        assert_eq!(found.formal.unwrap().identifier.id, "enableFoo");
+
+        // From a use, the function around it leads to the formal it names.
+        let found = node_at(&ast, source.rfind("Foo").unwrap()).unwrap();
+        let Expression::Identifier(used) = found.node else {
+            panic!("expected an identifier");
+        };
+        assert!(found.formal.is_none());
+        let Some(Expression::Function(function)) = found.parent() else {
+            panic!("expected a function");
+        };
+        let FunctionHead::FunctionHeadDestructured(head) = &function.head else {
+            panic!("expected a destructured head");
+        };
+        let definition = head.arguments.iter().find(|a| a.identifier.id == used.id).unwrap();
+        assert_eq!(definition.identifier.span.range(), 2..11);
+    }
+}
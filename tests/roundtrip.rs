@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::PathBuf;
+
+use nix_lens::format::{FormatOptions, format};
+use nix_lens::parser::ast::*;
+use nix_lens::parser::parser::parse;
+
+/// The Nix files under `tests/corpus`, with their contents.
+fn corpus() -> Vec<(PathBuf, String)> {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut files: Vec<(PathBuf, String)> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "nix"))
+        .map(|path| {
+            let source = fs::read_to_string(&path).unwrap();
+            (path, source)
+        })
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "the corpus is empty");
+    files
+}
+
+/// Renders `expressions` and parses the result back, expecting the same tree.
+fn assert_round_trip(name: &str, expressions: &[Expression]) {
+    for expression in expressions {
+        let rendered = expression
+            .render()
+            .unwrap_or_else(|error| panic!("{}: cannot render: {:?}", name, error));
+        let reparsed = parse(&rendered).unwrap_or_else(|error| {
+            panic!("{}: rendered invalid Nix:\n{}\n{:?}", name, rendered, error)
+        });
+        // Trees are not printed on failure: they are too large to read.
+        assert!(reparsed == [expression.clone()], "{}: rendered as\n{}", name, rendered);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corpus_renders_back() {
+        for (path, source) in corpus() {
+            let name = path.display().to_string();
+            let expressions =
+                parse(&source).unwrap_or_else(|error| panic!("{}: {:?}", name, error));
+            assert_round_trip(&name, &expressions);
+        }
+    }
+
+    #[test]
+    fn test_corpus_formats_back() {
+        for (path, source) in corpus() {
+            let name = path.display().to_string();
+            for width in [20, 100] {
+                let options = FormatOptions { width, ..FormatOptions::default() };
+                let formatted = format(&source, &options).unwrap();
+                assert!(parse(&formatted).unwrap() == parse(&source).unwrap(), "{}", name);
+                assert_eq!(format(&formatted, &options).unwrap(), formatted, "{}", name);
+            }
+        }
+    }
+
+    /// Every node in every file, not just whole files: a node must render to
+    /// valid Nix on its own.
+    #[test]
+    fn test_corpus_nodes_render_back() {
+        for (path, source) in corpus() {
+            let name = path.display().to_string();
+            let expressions = parse(&source).unwrap();
+            let mut stack: Vec<&Expression> = expressions.iter().collect();
+            while let Some(node) = stack.pop() {
+                stack.extend(node.children());
+                // Bindings and string parts only exist inside their parents.
+                if matches!(
+                    node,
+                    Expression::BindingKeyValue(_)
+                        | Expression::BindingInherit(_)
+                        | Expression::PartRaw(_)
+                        | Expression::PartInterpolation(_)
+                ) {
+                    continue;
+                }
+                assert_round_trip(&name, std::slice::from_ref(node));
+            }
+        }
+    }
+
+    #[test]
+    fn test_nodes_built_in_code() {
+        let identifier = |id: &str| Expression::Identifier(Identifier::new(id.to_string()));
+        let path = |ids: &[&str]| AttrPath::new(ids.iter().map(|id| identifier(id)).collect());
+        let nodes = vec![
+            Expression::List(List::new(vec![
+                identifier("a"),
+                Expression::FunctionApplication(FunctionApplication::new(
+                    identifier("f"),
+                    vec![identifier("x")],
+                )),
+                Expression::UnaryOperation(
+                    UnaryOperation::new(
+                        Operator::Negate(operators::Negate),
+                        Expression::Integer(Integer::new(1)),
+                    )
+                    .unwrap(),
+                ),
+                Expression::Null(Null::new()),
+            ])),
+            Expression::Assert(Assert::new(
+                identifier("c"),
+                Expression::NixString(NixString::new(vec![Expression::PartRaw(PartRaw::new(
+                    "m".to_string(),
+                ))])),
+            )),
+            Expression::PropertyAccess(PropertyAccess::new(
+                Expression::PropertyAccess(PropertyAccess::new(
+                    identifier("a"),
+                    path(&["b", "c"]),
+                    Some(identifier("d")),
+                )),
+                path(&["e"]),
+                Some(Expression::List(List::new(vec![]))),
+            )),
+            Expression::AttrSet(AttrSet::new(
+                false,
+                vec![
+                    Expression::BindingInherit(BindingInherit::new(
+                        Some(identifier("x")),
+                        vec![identifier("a"), identifier("b")],
+                    )),
+                    Expression::BindingInherit(BindingInherit::new(None, vec![identifier("c")])),
+                ],
+            )),
+            Expression::Function(Function::new(
+                FunctionHead::FunctionHeadDestructured(FunctionHeadDestructured::new(
+                    true,
+                    None,
+                    vec![
+                        FunctionHeadDestructuredArgument::new(
+                            Identifier::new("a".to_string()),
+                            None,
+                        ),
+                        FunctionHeadDestructuredArgument::new(
+                            Identifier::new("b".to_string()),
+                            Some(Expression::IfThenElse(IfThenElse::new(
+                                identifier("a"),
+                                identifier("a"),
+                                Expression::Integer(Integer::new(0)),
+                            ))),
+                        ),
+                    ],
+                )),
+                identifier("b"),
+            )),
+        ];
+        assert_round_trip("built in code", &nodes);
+
+        // The parser reads these back as the nodes it builds for the same code.
+        let string = |value: &str| Expression::LiteralString(LiteralString::new(value.to_string()));
+        let equivalents = [
+            (Expression::Throw(Throw::new(string("m"))), "throw \"m\""),
+            (
+                Expression::Throw(Throw::new(Expression::BinaryOperation(
+                    BinaryOperation::new(
+                        string("a"),
+                        Operator::Addition(operators::Addition),
+                        identifier("b"),
+                    )
+                    .unwrap(),
+                ))),
+                "throw (\"a\" + b)",
+            ),
+            (
+                Expression::List(List::new(vec![string("a\"${b}"), identifier("c")])),
+                "[ \"a\\\"\\${b}\" c ]",
+            ),
+        ];
+        for (node, source) in equivalents {
+            let rendered = node.render().unwrap();
+            assert_eq!(parse(&rendered).unwrap(), parse(source).unwrap(), "{}", rendered);
+        }
+    }
+
+    #[test]
+    fn test_literal_strings() {
+        for value in ["", "plain", "\"quoted\" \\ ${x} $y $${z}", "tab\tline\nreturn\r"] {
+            let rendered = LiteralString::new(value.to_string()).render();
+            let reparsed = parse(&rendered).unwrap();
+            match &reparsed[..] {
+                [Expression::NixString(string)] => {
+                    assert_eq!(string.value().as_deref(), Some(value), "{}", rendered)
+                }
+                other => panic!("{} parsed as {:?}", rendered, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use nix_lens::parser::ast::fold::{self, Fold};
+use nix_lens::parser::ast::visit::{self, Visitor, VisitorMut};
+use nix_lens::parser::ast::*;
+use nix_lens::parser::parser::parse;
+
+fn parse_one(input: &str) -> Expression {
+    parse(input).unwrap().remove(0)
+}
+
+#[derive(Default)]
+struct Identifiers {
+    names: Vec<String>,
+}
+
+impl Visitor for Identifiers {
+    fn visit_identifier(&mut self, node: &Identifier) {
+        self.names.push(node.id.clone());
+    }
+}
+
+/// Counts bindings, but does not look inside `let` blocks.
+#[derive(Default)]
+struct TopLevelBindings {
+    count: usize,
+}
+
+impl Visitor for TopLevelBindings {
+    fn visit_binding_key_value(&mut self, node: &BindingKeyValue) {
+        self.count += 1;
+        visit::walk_binding_key_value(self, node);
+    }
+
+    fn visit_let_in(&mut self, _node: &LetIn) {}
+}
+
+struct Rename<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+impl VisitorMut for Rename<'_> {
+    fn visit_identifier(&mut self, node: &mut Identifier) {
+        if node.id == self.from {
+            node.id = self.to.to_string();
+        }
+    }
+}
+
+/// Adds up integer additions.
+struct ConstantFold;
+
+impl Fold for ConstantFold {
+    fn fold_expression(&mut self, node: Expression) -> Expression {
+        match fold::walk_expression(self, node) {
+            Expression::BinaryOperation(BinaryOperation {
+                left,
+                operator: Operator::Addition(_),
+                right,
+                span,
+                ..
+            }) if matches!((&*left, &*right), (Expression::Integer(_), Expression::Integer(_))) => {
+                let (Expression::Integer(l), Expression::Integer(r)) = (*left, *right) else {
+                    unreachable!();
+                };
+                let value = l.value + r.value;
+                Expression::Integer(Integer::new_span(value, value.to_string(), span))
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visitor_order() {
+        let ast = parse_one("{ x ? y, ... }@args: f (g x).h or [ z ] ? w");
+        let mut identifiers = Identifiers::default();
+        identifiers.visit_expression(&ast);
+        assert_eq!(identifiers.names, ["x", "y", "args", "f", "g", "x", "h", "z", "w"]);
+    }
+
+    #[test]
+    fn test_visitor_can_skip_subtrees() {
+        let ast = parse_one("{ a = 1; b = let c = 2; in c; d.e = { f = 3; }; }");
+        let mut bindings = TopLevelBindings::default();
+        bindings.visit_expression(&ast);
+        assert_eq!(bindings.count, 4);
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let mut ast = parse_one("let pkgs = import <nixpkgs> { }; in pkgs: pkgs.hello");
+        Rename { from: "pkgs", to: "p" }.visit_expression(&mut ast);
+        assert_eq!(
+            ast.render().unwrap(),
+            "let p = import <nixpkgs> { }; in p: p.hello"
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        let ast = parse_one("{ a = 1 + 2 + 3; b = x + 1; c = \"${toString (4 + 5)}\"; }");
+        let folded = ConstantFold.fold_expression(ast);
+        assert_eq!(
+            folded.render().unwrap(),
+            "{ a = 6; b = x + 1; c = \"${toString 9}\"; }"
+        );
+    }
+}